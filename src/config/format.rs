@@ -1,14 +1,217 @@
+use std::fmt::{self, Write as FmtWrite};
 use std::path::Path;
 use std::fs::File;
 use std::io::{Error as IoError, Read};
 
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::ser::Error as SerError;
 use serde_yaml::Error as YamlError;
 use serde_json::Error as JsonError;
-use strum::{EnumString, EnumIter, AsRefStr};
+use toml::de::Error as TomlError;
+use toml::ser::Error as TomlSerError;
+use strum::{EnumString, EnumIter, AsRefStr, IntoEnumIterator};
 use thiserror::Error;
+use yaml_rust::{Yaml, YamlEmitter};
 
 use crate::metadata::{Arity, Schema, SchemaRepr};
+use crate::types::{Block, Decimal, Value};
+use crate::util::depth_limit::{DepthLimit, toml_nesting_exceeds};
+
+/// The default maximum nesting depth of metadata values, used when reading
+/// metadata without an explicit limit.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 64;
+
+/// Since TOML cannot have a top-level array, a sequence of blocks is read from
+/// an array of tables under this key instead (i.e. `[[items]]`).
+pub const TOML_SEQUENCE_KEY: &str = "items";
+
+/// Returns the nesting depth of a value, where scalars have a depth of 0, and
+/// sequences and mappings are one deeper than their deepest child.
+fn value_depth(value: &Value) -> usize {
+    match value {
+        Value::Sequence(seq) => 1 + seq.iter().map(value_depth).max().unwrap_or(0),
+        Value::Mapping(block) => block_depth(block),
+        _ => 0,
+    }
+}
+
+fn block_depth(block: &Block) -> usize {
+    1 + block.values().map(value_depth).max().unwrap_or(0)
+}
+
+fn schema_depth(schema: &Schema) -> usize {
+    // Blocks in a sequence or mapping schema are themselves one level deeper.
+    match schema {
+        Schema::One(block) => block_depth(block),
+        Schema::Seq(blocks) => 1 + blocks.iter().map(block_depth).max().unwrap_or(0),
+        Schema::Map(blocks) => 1 + blocks.values().map(block_depth).max().unwrap_or(0),
+    }
+}
+
+/// The indentation used when pretty-printing JSON.
+const JSON_INDENT: &str = "  ";
+
+/// Writes a decimal exactly, always with a decimal point so that it is read
+/// back as a decimal and not as an integer.
+fn decimal_text(d: &Decimal) -> String {
+    let s = d.to_string();
+
+    if s.contains('.') { s } else { format!("{}.0", s) }
+}
+
+/// Wraps metadata so that it is displayed as JSON, with decimals written
+/// exactly instead of as floats. The alternate flag (`{:#}`) pretty-prints it,
+/// matching the layout of `serde_json::to_string_pretty`.
+pub(crate) struct ExactJson<'a, T>(pub &'a T);
+
+impl fmt::Display for ExactJson<'_, Value> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        json_value(f, self.0, 0)
+    }
+}
+
+impl fmt::Display for ExactJson<'_, Block> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        json_block(f, self.0, 0)
+    }
+}
+
+impl fmt::Display for ExactJson<'_, Schema> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Schema::One(block) => json_block(f, block, 0),
+            Schema::Seq(block_seq) => {
+                json_container(f, ('[', ']'), block_seq.iter(), 0, json_block)
+            },
+            Schema::Map(block_map) => {
+                json_container(f, ('{', '}'), block_map.iter(), 0, |f, (k, b), level| {
+                    json_key(f, k)?;
+                    json_block(f, b, level)
+                })
+            },
+        }
+    }
+}
+
+fn json_value(f: &mut fmt::Formatter, value: &Value, level: usize) -> fmt::Result {
+    match value {
+        Value::Null => f.write_str("null"),
+        Value::String(s) => json_string(f, s),
+        Value::Integer(i) => write!(f, "{}", i),
+        Value::Boolean(b) => write!(f, "{}", b),
+        Value::Decimal(d) => f.write_str(&decimal_text(d)),
+        Value::Sequence(seq) => json_container(f, ('[', ']'), seq, level, json_value),
+        Value::Mapping(block) => json_block(f, block, level),
+    }
+}
+
+fn json_block(f: &mut fmt::Formatter, block: &Block, level: usize) -> fmt::Result {
+    json_container(f, ('{', '}'), block.iter(), level, |f, (k, v), level| {
+        json_key(f, k)?;
+        json_value(f, v, level)
+    })
+}
+
+/// Writes the items of a sequence or mapping between a pair of brackets,
+/// one per line when pretty-printing.
+fn json_container<I, F>(
+    f: &mut fmt::Formatter,
+    (open, close): (char, char),
+    items: I,
+    level: usize,
+    mut write_item: F,
+) -> fmt::Result
+where
+    I: IntoIterator,
+    F: FnMut(&mut fmt::Formatter, I::Item, usize) -> fmt::Result,
+{
+    let pretty = f.alternate();
+    let mut is_empty = true;
+
+    f.write_char(open)?;
+
+    for item in items {
+        if !is_empty {
+            f.write_char(',')?;
+        }
+
+        if pretty {
+            write!(f, "\n{}", JSON_INDENT.repeat(level + 1))?;
+        }
+
+        write_item(f, item, level + 1)?;
+        is_empty = false;
+    }
+
+    if pretty && !is_empty {
+        write!(f, "\n{}", JSON_INDENT.repeat(level))?;
+    }
+
+    f.write_char(close)
+}
+
+fn json_key(f: &mut fmt::Formatter, key: &str) -> fmt::Result {
+    json_string(f, key)?;
+    f.write_str(if f.alternate() { ": " } else { ":" })
+}
+
+/// Writes a quoted string, escaped in the same way as `serde_json`.
+fn json_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{08}' => f.write_str("\\b")?,
+            '\u{0C}' => f.write_str("\\f")?,
+            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+
+    f.write_char('"')
+}
+
+/// Converts metadata into a YAML document, writing decimals exactly.
+/// This matches how `serde_yaml` writes documents, apart from decimals, which
+/// it would write as floats.
+fn yaml_value(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::String(s) => Yaml::String(s.clone()),
+        Value::Integer(i) => Yaml::Integer(*i),
+        Value::Boolean(b) => Yaml::Boolean(*b),
+        Value::Decimal(d) => Yaml::Real(decimal_text(d)),
+        Value::Sequence(seq) => Yaml::Array(seq.iter().map(yaml_value).collect()),
+        Value::Mapping(block) => yaml_block(block),
+    }
+}
+
+fn yaml_block(block: &Block) -> Yaml {
+    Yaml::Hash(block.iter().map(|(k, v)| (Yaml::String(k.clone()), yaml_value(v))).collect())
+}
+
+fn yaml_schema(schema: &Schema) -> Yaml {
+    match schema {
+        Schema::One(block) => yaml_block(block),
+        Schema::Seq(block_seq) => Yaml::Array(block_seq.iter().map(yaml_block).collect()),
+        Schema::Map(block_map) => {
+            Yaml::Hash(block_map.iter().map(|(k, b)| (Yaml::String(k.clone()), yaml_block(b))).collect())
+        },
+    }
+}
+
+fn emit_yaml(doc: &Yaml) -> Result<String, YamlError> {
+    let mut s = String::new();
+    YamlEmitter::new(&mut s).dump(doc).map_err(YamlError::custom)?;
+    s.push('\n');
+    Ok(s)
+}
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -20,6 +223,20 @@ pub enum Error {
     YamlDeserialize(#[source] YamlError),
     #[error("cannot deserialize JSON: {0}")]
     JsonDeserialize(#[source] JsonError),
+    #[error("cannot deserialize TOML: {0}")]
+    TomlDeserialize(#[source] TomlError),
+    #[error("cannot write metadata file: {0}")]
+    CannotWriteFile(#[source] IoError),
+    #[error("cannot serialize YAML: {0}")]
+    YamlSerialize(#[source] YamlError),
+    #[error("cannot serialize JSON: {0}")]
+    JsonSerialize(#[source] JsonError),
+    #[error("cannot serialize TOML: {0}")]
+    TomlSerialize(#[source] TomlSerError),
+    #[error("cannot serialize TOML: top level must be a mapping")]
+    TomlNotTable,
+    #[error("metadata exceeds the maximum nesting depth of {0}")]
+    DepthExceeded(usize),
 }
 
 /// Represents all the different metadata formats that are supported.
@@ -30,37 +247,195 @@ pub enum Format {
     Json,
     #[strum(serialize = "YML", serialize = "yml")]
     Yaml,
+    #[strum(serialize = "TOML", serialize = "toml")]
+    Toml,
 }
 
 impl Format {
-    fn read_yaml(s: &str, arity: &Arity) -> Result<Schema, YamlError> {
-        match arity {
-            Arity::Unit => serde_yaml::from_str(s).map(SchemaRepr::Unit),
-            Arity::Many => serde_yaml::from_str(s).map(SchemaRepr::Many),
-        }.map(Into::into)
+    /// Returns all of the file extensions that are recognized for this format,
+    /// without the leading dot. The first one is the canonical extension.
+    pub fn file_extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Json => &["json"],
+            Self::Yaml => &["yml", "yaml"],
+            Self::Toml => &["toml"],
+        }
+    }
+
+    /// Returns the canonical file extension for this format, used when
+    /// creating new file names.
+    pub fn default_file_extension(&self) -> &'static str {
+        self.file_extensions()[0]
+    }
+
+    /// Finds the format that recognizes a file extension, ignoring ASCII case.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        Self::iter().find(|fmt| {
+            fmt.file_extensions().iter().any(|fe| fe.eq_ignore_ascii_case(ext))
+        })
+    }
+
+    /// Deserializes text in this format, failing as soon as sequences and
+    /// mappings are nested past a maximum depth.
+    fn read_limited<T: DeserializeOwned>(&self, s: &str, max_depth: usize) -> Result<T, Error> {
+        let limit = DepthLimit::new(max_depth);
+
+        let result = match self {
+            Self::Yaml => {
+                limit.deserialize(serde_yaml::Deserializer::from_str(s)).map_err(Error::YamlDeserialize)
+            },
+            Self::Json => {
+                let mut de = serde_json::Deserializer::from_str(s);
+                limit.deserialize(&mut de)
+                    .and_then(|v| de.end().map(|_| v))
+                    .map_err(Error::JsonDeserialize)
+            },
+            Self::Toml => {
+                // The TOML parser recurses into nested arrays and inline tables
+                // before deserializing, so those need to be checked beforehand.
+                if toml_nesting_exceeds(s, max_depth) {
+                    return Err(Error::DepthExceeded(max_depth));
+                }
+
+                limit.deserialize(&mut toml::Deserializer::new(s)).map_err(Error::TomlDeserialize)
+            },
+        };
+
+        if limit.is_exceeded() {
+            return Err(Error::DepthExceeded(max_depth));
+        }
+
+        result
     }
 
-    fn read_json(s: &str, arity: &Arity) -> Result<Schema, JsonError> {
-        match arity {
-            Arity::Unit => serde_json::from_str(s).map(SchemaRepr::Unit),
-            Arity::Many => serde_json::from_str(s).map(SchemaRepr::Many),
-        }.map(Into::into)
+    /// Reads TOML metadata with many items. Since TOML cannot have a top-level
+    /// array, a sequence of blocks is read from a lone `[[items]]` array of
+    /// tables, and any other table is read as a mapping of item names to blocks.
+    fn read_toml_many(s: &str, max_depth: usize) -> Result<Schema, Error> {
+        // The `[[items]]` key adds a level of nesting to a sequence of blocks,
+        // so the exact depth can only be checked once it is known which one
+        // this is.
+        let table: toml::value::Table = Self::Toml.read_limited(s, max_depth + 1)
+            .map_err(|e| match e {
+                Error::DepthExceeded(_) => Error::DepthExceeded(max_depth),
+                e => e,
+            })?;
+
+        let schema = match table.get(TOML_SEQUENCE_KEY) {
+            Some(items @ toml::Value::Array(_)) if table.len() == 1 => {
+                items.clone().try_into().map(Schema::Seq)
+            },
+            _ => toml::Value::Table(table).try_into().map(Schema::Map),
+        }.map_err(Error::TomlDeserialize)?;
+
+        if schema_depth(&schema) > max_depth {
+            return Err(Error::DepthExceeded(max_depth));
+        }
+
+        Ok(schema)
     }
 
     pub fn read_schema_str(&self, s: &str, arity: &Arity) -> Result<Schema, Error> {
-        match self {
-            Self::Yaml => Self::read_yaml(s, arity).map_err(Error::YamlDeserialize),
-            Self::Json => Self::read_json(s, arity).map_err(Error::JsonDeserialize),
+        self.read_schema_str_with_max_depth(s, arity, DEFAULT_MAX_NESTING_DEPTH)
+    }
+
+    /// Similar to `read_schema_str`, but with an explicit maximum nesting
+    /// depth, past which an error is returned.
+    /// The limit is enforced while reading, so very deeply nested input is
+    /// rejected without being fully parsed.
+    pub fn read_schema_str_with_max_depth(
+        &self,
+        s: &str,
+        arity: &Arity,
+        max_depth: usize,
+    ) -> Result<Schema, Error> {
+        match (self, arity) {
+            (Self::Toml, Arity::Many) => Self::read_toml_many(s, max_depth),
+            (_, Arity::Unit) => self.read_limited(s, max_depth).map(SchemaRepr::Unit).map(Into::into),
+            (_, Arity::Many) => self.read_limited(s, max_depth).map(SchemaRepr::Many).map(Into::into),
         }
     }
 
     pub fn read_schema_path(&self, path: &Path, arity: &Arity) -> Result<Schema, Error> {
+        self.read_schema_str(&Self::read_path_to_string(path)?, arity)
+    }
+
+    /// Reads metadata as a raw untyped value, without requiring it to be in the
+    /// shape of a schema. The top level may be a mapping, sequence, or scalar.
+    pub fn read_raw_str(&self, s: &str) -> Result<Value, Error> {
+        self.read_raw_str_with_max_depth(s, DEFAULT_MAX_NESTING_DEPTH)
+    }
+
+    /// Similar to `read_raw_str`, but with an explicit maximum nesting depth,
+    /// past which an error is returned.
+    pub fn read_raw_str_with_max_depth(&self, s: &str, max_depth: usize) -> Result<Value, Error> {
+        self.read_limited(s, max_depth)
+    }
+
+    pub fn read_raw_path(&self, path: &Path) -> Result<Value, Error> {
+        self.read_raw_str(&Self::read_path_to_string(path)?)
+    }
+
+    /// Writes TOML metadata. A sequence of blocks is written as an array of
+    /// tables under the `[[items]]` key, matching how it is read.
+    fn write_toml(schema: &Schema) -> Result<String, TomlSerError> {
+        // Converting to a TOML value first ensures that plain values are
+        // written before any tables, which TOML requires.
+        let value = match schema {
+            Schema::Seq(block_seq) => {
+                let mut table = toml::value::Table::new();
+                table.insert(String::from(TOML_SEQUENCE_KEY), toml::Value::try_from(block_seq)?);
+                toml::Value::Table(table)
+            },
+            _ => toml::Value::try_from(schema)?,
+        };
+
+        toml::to_string(&value)
+    }
+
+    /// Serializes a schema into text in this format, which can be read back
+    /// using `read_schema_str` with the matching arity.
+    /// Decimals are written exactly in YAML and JSON, while TOML only supports
+    /// floats. Note that TOML does not support null values, so serializing a
+    /// schema that contains them to TOML is an error.
+    pub fn write_schema_str(&self, schema: &Schema) -> Result<String, Error> {
+        match self {
+            Self::Yaml => emit_yaml(&yaml_schema(schema)).map_err(Error::YamlSerialize),
+            Self::Json => Ok(format!("{:#}", ExactJson(schema))),
+            Self::Toml => Self::write_toml(schema).map_err(Error::TomlSerialize),
+        }
+    }
+
+    pub fn write_schema_path(&self, path: &Path, schema: &Schema) -> Result<(), Error> {
+        let s = self.write_schema_str(schema)?;
+        std::fs::write(path, s).map_err(Error::CannotWriteFile)
+    }
+
+    /// Serializes a raw untyped value into text in this format.
+    /// Since TOML requires a table at the top level, only mappings can be
+    /// serialized to TOML.
+    pub fn write_raw_str(&self, value: &Value) -> Result<String, Error> {
+        match self {
+            Self::Yaml => emit_yaml(&yaml_value(value)).map_err(Error::YamlSerialize),
+            Self::Json => Ok(format!("{:#}", ExactJson(value))),
+            Self::Toml => match value {
+                Value::Mapping(..) => {
+                    toml::Value::try_from(value)
+                        .and_then(|v| toml::to_string(&v))
+                        .map_err(Error::TomlSerialize)
+                },
+                _ => Err(Error::TomlNotTable),
+            },
+        }
+    }
+
+    fn read_path_to_string(path: &Path) -> Result<String, Error> {
         let mut f = File::open(path).map_err(Error::CannotOpenFile)?;
 
         let mut buffer = String::new();
         f.read_to_string(&mut buffer).map_err(Error::CannotReadFile)?;
 
-        self.read_schema_str(&buffer, arity)
+        Ok(buffer)
     }
 }
 
@@ -76,7 +451,7 @@ mod tests {
             key_c: val_c
             key_d: val_d
         "#;
-        assert!(matches!(Format::read_yaml(input, &Arity::Unit), Ok(Schema::One(_))));
+        assert!(matches!(Format::Yaml.read_schema_str(input, &Arity::Unit), Ok(Schema::One(_))));
 
         let input = r#"
             key_a: val_a
@@ -89,7 +464,7 @@ mod tests {
                 -   val_a
                 -   val_b
         "#;
-        assert!(matches!(Format::read_yaml(input, &Arity::Unit), Ok(Schema::One(_))));
+        assert!(matches!(Format::Yaml.read_schema_str(input, &Arity::Unit), Ok(Schema::One(_))));
 
         let input = r#"
             -   key_1_a: val_1_a
@@ -97,7 +472,7 @@ mod tests {
             -   key_2_a: val_2_a
                 key_2_b: val_2_b
         "#;
-        assert!(matches!(Format::read_yaml(input, &Arity::Many), Ok(Schema::Seq(_))));
+        assert!(matches!(Format::Yaml.read_schema_str(input, &Arity::Many), Ok(Schema::Seq(_))));
 
         let input = r#"
             item_1:
@@ -107,7 +482,7 @@ mod tests {
                 key_2_a: val_2_a
                 key_2_b: val_2_b
         "#;
-        assert!(matches!(Format::read_yaml(input, &Arity::Many), Ok(Schema::Map(_))));
+        assert!(matches!(Format::Yaml.read_schema_str(input, &Arity::Many), Ok(Schema::Map(_))));
     }
 
     #[test]
@@ -120,7 +495,7 @@ mod tests {
             "key_d": "val_d"
         }
         "#;
-        assert!(matches!(Format::read_json(input, &Arity::Unit), Ok(Schema::One(_))));
+        assert!(matches!(Format::Json.read_schema_str(input, &Arity::Unit), Ok(Schema::One(_))));
 
         let input = r#"
         {
@@ -143,7 +518,7 @@ mod tests {
             ]
         }
         "#;
-        assert!(matches!(Format::read_json(input, &Arity::Unit), Ok(Schema::One(_))));
+        assert!(matches!(Format::Json.read_schema_str(input, &Arity::Unit), Ok(Schema::One(_))));
 
         let input = r#"
         [
@@ -157,7 +532,7 @@ mod tests {
             }
         ]
         "#;
-        assert!(matches!(Format::read_json(input, &Arity::Many), Ok(Schema::Seq(_))));
+        assert!(matches!(Format::Json.read_schema_str(input, &Arity::Many), Ok(Schema::Seq(_))));
 
         let input = r#"
         {
@@ -171,6 +546,379 @@ mod tests {
             }
         }
         "#;
-        assert!(matches!(Format::read_json(input, &Arity::Many), Ok(Schema::Map(_))));
+        assert!(matches!(Format::Json.read_schema_str(input, &Arity::Many), Ok(Schema::Map(_))));
+    }
+
+    #[test]
+    fn read_toml() {
+        let input = r#"
+            key_a = "val_a"
+            key_b = 27
+            key_c = ["val_a", "val_b"]
+
+            [key_d]
+            sub_key_a = "sub_val_a"
+            sub_key_b = true
+        "#;
+        assert!(matches!(Format::Toml.read_schema_str(input, &Arity::Unit), Ok(Schema::One(_))));
+
+        let input = r#"
+            [[items]]
+            key_1_a = "val_1_a"
+            key_1_b = "val_1_b"
+
+            [[items]]
+            key_2_a = "val_2_a"
+            key_2_b = "val_2_b"
+        "#;
+        assert!(matches!(Format::Toml.read_schema_str(input, &Arity::Many), Ok(Schema::Seq(ref s)) if s.len() == 2));
+
+        let input = r#"
+            [item_1]
+            key_1_a = "val_1_a"
+            key_1_b = "val_1_b"
+
+            [item_2]
+            key_2_a = "val_2_a"
+            key_2_b = "val_2_b"
+        "#;
+        assert!(matches!(Format::Toml.read_schema_str(input, &Arity::Many), Ok(Schema::Map(ref m)) if m.len() == 2));
+
+        // An item that happens to be named "items" is still read as a mapping.
+        let input = r#"
+            [items]
+            key = "val"
+        "#;
+        assert!(matches!(Format::Toml.read_schema_str(input, &Arity::Many), Ok(Schema::Map(_))));
+
+        // Entries that are not tables cannot be blocks.
+        assert!(Format::Toml.read_schema_str("key = \"val\"", &Arity::Many).is_err());
+        assert!(Format::Toml.read_schema_str("items = [1, 2]", &Arity::Many).is_err());
+        assert!(matches!(
+            Format::Toml.read_schema_str("[unclosed", &Arity::Unit),
+            Err(Error::TomlDeserialize(_))
+        ));
+    }
+
+    #[test]
+    fn toml_matches_other_formats() {
+        let toml_input = r#"
+            [[items]]
+            title = "Track 1"
+            number = 1
+            tags = ["a", "b"]
+
+            [[items]]
+            title = "Track 2"
+            number = 2
+
+            [items.extra]
+            live = false
+        "#;
+        let json_input = r#"
+        [
+            {"title": "Track 1", "number": 1, "tags": ["a", "b"]},
+            {"title": "Track 2", "number": 2, "extra": {"live": false}}
+        ]
+        "#;
+
+        let from_toml = Format::Toml.read_schema_str(toml_input, &Arity::Many).unwrap();
+        let from_json = Format::Json.read_schema_str(json_input, &Arity::Many).unwrap();
+        assert_eq!(from_toml, from_json);
+
+        let toml_input = r#"
+            title = "Album"
+            [artist]
+            name = "Someone"
+        "#;
+        let yaml_input = "title: Album\nartist:\n  name: Someone";
+        assert_eq!(
+            Format::Toml.read_raw_str(toml_input).unwrap(),
+            Format::Yaml.read_raw_str(yaml_input).unwrap(),
+        );
+    }
+
+    #[test]
+    fn write_schema_str() {
+        use rust_decimal_macros::dec;
+
+        let block_a = Block(maplit::btreemap![
+            String::from("title") => Value::String(String::from("Track 1")),
+            String::from("number") => Value::Integer(1),
+            String::from("gain") => Value::Decimal(dec!(-3.25)),
+            String::from("live") => Value::Boolean(false),
+            String::from("tags") => Value::Sequence(vec![
+                Value::String(String::from("a")),
+                Value::Sequence(vec![Value::Integer(2), Value::Decimal(dec!(0.5))]),
+            ]),
+            String::from("credits") => Value::Mapping(Block(maplit::btreemap![
+                String::from("artist") => Value::String(String::from("Someone")),
+                String::from("roles") => Value::Mapping(Block(maplit::btreemap![
+                    String::from("lead") => Value::Boolean(true),
+                ])),
+            ])),
+        ]);
+        let mut block_b = block_a.clone();
+        block_b.insert(String::from("title"), Value::String(String::from("Track 2")));
+
+        let schemas_and_arities = vec![
+            (Schema::One(block_a.clone()), Arity::Unit),
+            (Schema::Seq(vec![block_a.clone(), block_b.clone()].into_iter().collect()), Arity::Many),
+            (Schema::Map(vec![
+                (String::from("item_1"), block_a.clone()),
+                (String::from("item_2"), block_b.clone()),
+            ].into_iter().collect()), Arity::Many),
+        ];
+
+        for fmt in Format::iter() {
+            for (schema, arity) in &schemas_and_arities {
+                let text = fmt.write_schema_str(schema).unwrap();
+                let produced = fmt.read_schema_str(&text, arity).unwrap();
+                assert_eq!(&produced, schema, "{:?}:\n{}", fmt, text);
+            }
+        }
+
+        // Round trip starting from text.
+        let input = "key_a: val_a\nkey_b:\n  sub_key: [1, 2.5, ~]\n";
+        let schema = Format::Yaml.read_schema_str(input, &Arity::Unit).unwrap();
+        let text = Format::Yaml.write_schema_str(&schema).unwrap();
+        assert_eq!(Format::Yaml.read_schema_str(&text, &Arity::Unit).unwrap(), schema);
+
+        // TOML cannot represent nulls.
+        let block = Block(maplit::btreemap![String::from("key") => Value::Null]);
+        assert!(matches!(
+            Format::Toml.write_schema_str(&Schema::One(block)),
+            Err(Error::TomlSerialize(_))
+        ));
+    }
+
+    #[test]
+    fn decimal_precision() {
+        use rust_decimal_macros::dec;
+
+        // More significant digits than a float can hold.
+        let exact = dec!(0.1234567890123456789);
+        let value = Value::Mapping(Block(maplit::btreemap![
+            String::from("gain") => Value::Decimal(exact),
+            String::from("whole") => Value::Decimal(dec!(27)),
+            String::from("nested") => Value::Sequence(vec![
+                Value::Decimal(-exact),
+                Value::Mapping(Block(maplit::btreemap![
+                    String::from("key") => Value::Decimal(dec!(12345678901234567.89)),
+                ])),
+            ]),
+        ]));
+        let block = match &value {
+            Value::Mapping(block) => block.clone(),
+            _ => unreachable!(),
+        };
+
+        let schemas = [
+            Schema::One(block.clone()),
+            Schema::Seq(vec![block.clone(), Block::new()].into_iter().collect()),
+            Schema::Map(vec![(String::from("item"), block)].into_iter().collect()),
+        ];
+
+        for fmt in &[Format::Yaml, Format::Json] {
+            let texts = std::iter::once(fmt.write_raw_str(&value).unwrap())
+                .chain(schemas.iter().map(|schema| fmt.write_schema_str(schema).unwrap()));
+
+            for text in texts {
+                assert!(text.contains("0.1234567890123456789"), "{:?}:\n{}", fmt, text);
+                assert!(text.contains("-0.1234567890123456789"), "{:?}:\n{}", fmt, text);
+                assert!(text.contains("12345678901234567.89"), "{:?}:\n{}", fmt, text);
+                assert!(text.contains("27.0"), "{:?}:\n{}", fmt, text);
+            }
+        }
+
+        // Whole decimals are written with a decimal point, so that they are
+        // read back as decimals.
+        let text = Format::Json.write_raw_str(&Value::Decimal(dec!(27))).unwrap();
+        assert_eq!(text, "27.0");
+        assert_eq!(Format::Json.read_raw_str(&text).unwrap(), Value::Decimal(dec!(27)));
+    }
+
+    #[test]
+    fn write_json() {
+        // The layout and escaping match those of `serde_json`.
+        let value = Format::Json.read_raw_str(r#"{
+            "str": "a \"quoted\" \\ line\n\t\u0001\u00e9",
+            "seq": [1, [], {}, [true, null]],
+            "map": {"b": {"c": -2}}
+        }"#).unwrap();
+
+        assert_eq!(Format::Json.write_raw_str(&value).unwrap(), serde_json::to_string_pretty(&value).unwrap());
+        assert_eq!(ExactJson(&value).to_string(), serde_json::to_string(&value).unwrap());
+
+        let block = match &value {
+            Value::Mapping(block) => block.clone(),
+            _ => unreachable!(),
+        };
+        let schema = Schema::Map(vec![(String::from("item"), block)].into_iter().collect());
+        assert_eq!(Format::Json.write_schema_str(&schema).unwrap(), serde_json::to_string_pretty(&schema).unwrap());
+    }
+
+    #[test]
+    fn write_raw_str() {
+        // Arrays in TOML must not mix value types.
+        let value = Format::Json.read_raw_str(r#"{"key": [1, 2], "sub": {"a": "b", "c": 2.5}}"#).unwrap();
+
+        for fmt in Format::iter() {
+            let text = fmt.write_raw_str(&value).unwrap();
+            assert_eq!(fmt.read_raw_str(&text).unwrap(), value);
+        }
+
+        // TOML requires a top-level table.
+        assert!(matches!(Format::Toml.write_raw_str(&Value::Integer(1)), Err(Error::TomlNotTable)));
+        assert!(Format::Json.write_raw_str(&Value::Integer(1)).is_ok());
+    }
+
+    #[test]
+    fn file_extensions() {
+        assert_eq!(Format::Json.file_extensions(), &["json"]);
+        assert_eq!(Format::Yaml.file_extensions(), &["yml", "yaml"]);
+        assert_eq!(Format::Toml.file_extensions(), &["toml"]);
+
+        assert_eq!(Format::Json.default_file_extension(), "json");
+        assert_eq!(Format::Yaml.default_file_extension(), "yml");
+        assert_eq!(Format::Toml.default_file_extension(), "toml");
+
+        assert_eq!(Format::from_extension("json"), Some(Format::Json));
+        assert_eq!(Format::from_extension("yml"), Some(Format::Yaml));
+        assert_eq!(Format::from_extension("yaml"), Some(Format::Yaml));
+        assert_eq!(Format::from_extension("YAML"), Some(Format::Yaml));
+        assert_eq!(Format::from_extension("toml"), Some(Format::Toml));
+        assert_eq!(Format::from_extension("JSON"), Some(Format::Json));
+        assert_eq!(Format::from_extension("Yml"), Some(Format::Yaml));
+        assert_eq!(Format::from_extension("TOML"), Some(Format::Toml));
+        assert_eq!(Format::from_extension("txt"), None);
+        assert_eq!(Format::from_extension(".json"), None);
+        assert_eq!(Format::from_extension(""), None);
+    }
+
+    #[test]
+    fn read_raw_str() {
+        // Top-level scalars.
+        assert_eq!(Format::Yaml.read_raw_str("27").unwrap(), Value::Integer(27));
+        assert_eq!(Format::Yaml.read_raw_str("hello").unwrap(), Value::String(String::from("hello")));
+        assert_eq!(Format::Json.read_raw_str("true").unwrap(), Value::Boolean(true));
+        assert_eq!(Format::Json.read_raw_str("null").unwrap(), Value::Null);
+
+        // Top-level sequences, including ones that are not block sequences.
+        let expected = Value::Sequence(vec![
+            Value::String(String::from("a")),
+            Value::Integer(1),
+            Value::Sequence(vec![Value::Boolean(false)]),
+        ]);
+        assert_eq!(Format::Yaml.read_raw_str("- a\n- 1\n- [false]").unwrap(), expected);
+        assert_eq!(Format::Json.read_raw_str(r#"["a", 1, [false]]"#).unwrap(), expected);
+
+        // Top-level mappings.
+        let produced = Format::Json.read_raw_str(r#"{"key": "val"}"#).unwrap();
+        assert_eq!(
+            produced.get_key_path(&["key"]),
+            Some(&Value::String(String::from("val"))),
+        );
+
+        // Invalid input is still an error.
+        assert!(matches!(Format::Json.read_raw_str("[1, 2"), Err(Error::JsonDeserialize(_))));
+    }
+
+    #[test]
+    fn max_nesting_depth() {
+        // Creates a block nested to a given depth.
+        fn nested_json(depth: usize) -> String {
+            let mut s = String::from("1");
+            for _ in 0..depth {
+                s = format!(r#"{{"k": {}}}"#, s);
+            }
+            s
+        }
+
+        for fmt in &[Format::Json, Format::Yaml] {
+            // JSON is also valid YAML.
+            let input = nested_json(5);
+
+            assert!(fmt.read_schema_str_with_max_depth(&input, &Arity::Unit, 5).is_ok());
+            assert!(matches!(
+                fmt.read_schema_str_with_max_depth(&input, &Arity::Unit, 4),
+                Err(Error::DepthExceeded(4))
+            ));
+
+            assert!(fmt.read_raw_str_with_max_depth(&input, 5).is_ok());
+            assert!(matches!(
+                fmt.read_raw_str_with_max_depth(&input, 4),
+                Err(Error::DepthExceeded(4))
+            ));
+
+            // The blocks of a sequence schema are one level deeper.
+            let input = format!("[{}]", nested_json(5));
+            assert!(fmt.read_schema_str_with_max_depth(&input, &Arity::Many, 6).is_ok());
+            assert!(matches!(
+                fmt.read_schema_str_with_max_depth(&input, &Arity::Many, 5),
+                Err(Error::DepthExceeded(5))
+            ));
+
+            // The default limit is generous, but still enforced.
+            let input = nested_json(DEFAULT_MAX_NESTING_DEPTH);
+            assert!(fmt.read_schema_str(&input, &Arity::Unit).is_ok());
+            let input = nested_json(DEFAULT_MAX_NESTING_DEPTH + 1);
+            assert!(matches!(
+                fmt.read_schema_str(&input, &Arity::Unit),
+                Err(Error::DepthExceeded(DEFAULT_MAX_NESTING_DEPTH))
+            ));
+        }
+
+        // TOML is checked with nested arrays and inline tables.
+        let input = "a = [[{b = [1]}]]";
+        assert!(Format::Toml.read_raw_str_with_max_depth(input, 5).is_ok());
+        assert!(matches!(Format::Toml.read_raw_str_with_max_depth(input, 4), Err(Error::DepthExceeded(4))));
+        assert!(Format::Toml.read_schema_str_with_max_depth(input, &Arity::Unit, 5).is_ok());
+        assert!(matches!(
+            Format::Toml.read_schema_str_with_max_depth(input, &Arity::Unit, 4),
+            Err(Error::DepthExceeded(4))
+        ));
+
+        // The `[[items]]` key does not count towards the depth of a sequence.
+        let input = "[[items]]\na = [[1]]";
+        assert!(Format::Toml.read_schema_str_with_max_depth(input, &Arity::Many, 4).is_ok());
+        assert!(matches!(
+            Format::Toml.read_schema_str_with_max_depth(input, &Arity::Many, 3),
+            Err(Error::DepthExceeded(3))
+        ));
+        let input = "[item]\na = [[1]]";
+        assert!(Format::Toml.read_schema_str_with_max_depth(input, &Arity::Many, 4).is_ok());
+        assert!(matches!(
+            Format::Toml.read_schema_str_with_max_depth(input, &Arity::Many, 3),
+            Err(Error::DepthExceeded(3))
+        ));
+    }
+
+    #[test]
+    fn max_nesting_depth_deep_input() {
+        // Input this deep overflows the stack if it is fully parsed first.
+        fn nested(depth: usize) -> String {
+            format!("{}{}", "[".repeat(depth), "]".repeat(depth))
+        }
+
+        // The YAML scanner refuses to nest flow sequences past a few hundred
+        // levels on its own, so YAML uses input that it is able to scan.
+        let inputs = vec![
+            (Format::Json, format!(r#"{{"k": {}}}"#, nested(100_000))),
+            (Format::Yaml, format!("k: {}", nested(200))),
+            (Format::Toml, format!("k = {}", nested(100_000))),
+        ];
+
+        for (fmt, input) in &inputs {
+            assert!(matches!(fmt.read_raw_str(input), Err(Error::DepthExceeded(DEFAULT_MAX_NESTING_DEPTH))));
+            assert!(matches!(
+                fmt.read_schema_str(input, &Arity::Unit),
+                Err(Error::DepthExceeded(DEFAULT_MAX_NESTING_DEPTH))
+            ));
+            assert!(matches!(
+                fmt.read_schema_str(input, &Arity::Many),
+                Err(Error::DepthExceeded(DEFAULT_MAX_NESTING_DEPTH))
+            ));
+        }
     }
 }
@@ -5,18 +5,19 @@ pub mod selection;
 pub mod sorter;
 
 pub use self::format::{Format, Error as FormatError};
-pub use self::selection::Selection;
+pub use self::selection::{Selection, SelectionExplanation, SelectionLint};
 pub use self::sorter::Sorter;
 
 use std::convert::{TryFrom, TryInto};
-use std::path::Path;
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
 
 use serde::Deserialize;
 use thiserror::Error;
 
 use self::selection::{SelectionRepr, MatcherError};
 
-use crate::sources::{Anchor, Source, Sourcer, CreateError as SourceCreateError};
+use crate::sources::{Anchor, MultiFormat, Source, Sourcer, CreateError as SourceCreateError};
 
 const DEFAULT_INTERNAL_STUB: &str = "album";
 const DEFAULT_EXTERNAL_STUB: &str = "track";
@@ -29,6 +30,28 @@ pub enum Error {
     Source(#[from] SourceCreateError),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub enum ValidationError {
+    #[error("meta file name is empty")]
+    EmptySourceName,
+    #[error(r#"meta file name is used by more than one source: "{0}""#)]
+    DuplicateSourceName(String),
+    #[error(r#"meta file name does not have an extension for its format ({1:?}): "{0}""#)]
+    FormatMismatch(String, Format),
+}
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("cannot read config file: {0}")]
+    CannotReadFile(#[source] IoError),
+    #[error("unknown config file extension: {}", .0.display())]
+    UnknownExtension(PathBuf),
+    #[error("cannot parse config file: {0}")]
+    CannotParse(#[source] FormatError),
+    #[error("invalid config: {0}")]
+    Invalid(#[source] ValidationError),
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct SourcesRepr {
@@ -36,17 +59,19 @@ pub struct SourcesRepr {
     external: Vec<String>,
     #[serde(rename = "album")]
     internal: Vec<String>,
+    index: Option<String>,
+    multi_format: MultiFormat,
 }
 
 impl Default for SourcesRepr {
     fn default() -> Self {
         let default_fmt = Format::Json;
-        let default_ext = default_fmt.as_ref();
+        let default_ext = default_fmt.default_file_extension();
 
         let external = vec![format!("{}.{}", DEFAULT_EXTERNAL_STUB, default_ext)];
         let internal = vec![format!("{}.{}", DEFAULT_INTERNAL_STUB, default_ext)];
 
-        Self { external, internal, }
+        Self { external, internal, index: None, multi_format: MultiFormat::default(), }
     }
 }
 
@@ -87,9 +112,14 @@ impl TryFrom<ConfigRepr> for Config {
             sources.push(src);
         }
 
+        let index = match value.sources_repr.index {
+            Some(name) => Some(Source::from_name(name, Anchor::External)?),
+            None => None,
+        };
+
         if selection_repr.exclude_sources {
             // Add sources to the list of excluded files.
-            for source in sources.iter() {
+            for source in sources.iter().chain(index.iter()) {
                 let pattern = &source.name;
                 selection_repr.exclude_files.add_pattern(pattern).map_err(Into::<MatcherError>::into)?;
             }
@@ -98,7 +128,9 @@ impl TryFrom<ConfigRepr> for Config {
         // Manually convert `SelectionRepr` into `Selection`.
         let selection = selection_repr.try_into()?;
 
-        let sourcer = sources.into();
+        let sourcer = Sourcer::from(sources)
+            .with_multi_format(value.sources_repr.multi_format)
+            .with_index(index);
 
         Ok(Self {
             selection,
@@ -121,9 +153,72 @@ impl Config {
         Ok(config)
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: &P) -> Result<Self, Box<dyn std::error::Error>> {
-        let contents = std::fs::read_to_string(path)?;
-        Self::from_str(&contents)
+    /// Loads a config file, picking its format based on its file extension.
+    /// YAML, JSON, and TOML config files are supported.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+
+        let format = path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Format::from_extension)
+            .ok_or_else(|| LoadError::UnknownExtension(path.to_path_buf()))?;
+
+        let contents = std::fs::read_to_string(path).map_err(LoadError::CannotReadFile)?;
+
+        let config: Self = match format {
+            Format::Yaml => serde_yaml::from_str(&contents).map_err(FormatError::YamlDeserialize),
+            Format::Json => serde_json::from_str(&contents).map_err(FormatError::JsonDeserialize),
+            Format::Toml => toml::from_str(&contents).map_err(FormatError::TomlDeserialize),
+        }.map_err(LoadError::CannotParse)?;
+
+        config.validate().map_err(LoadError::Invalid)?;
+
+        Ok(config)
+    }
+
+    /// Checks the meta file sources of this config for settings that would
+    /// make metadata ambiguous. Every source, including the index, must have a
+    /// non-empty and distinct name, whose extension matches its format.
+    /// For example, using the same name for both item and self metadata would
+    /// make a meta file inside a directory provide metadata for both that
+    /// directory and its children.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut seen_names = std::collections::HashSet::new();
+
+        for source in self.sourcer.as_sources().iter().chain(self.sourcer.index()) {
+            if source.name.is_empty() {
+                return Err(ValidationError::EmptySourceName);
+            }
+
+            if !seen_names.insert(source.name.as_str()) {
+                return Err(ValidationError::DuplicateSourceName(source.name.clone()));
+            }
+
+            let ext_format = source.name.rsplit('.').next().and_then(Format::from_extension);
+
+            if ext_format != Some(source.format) {
+                return Err(ValidationError::FormatMismatch(source.name.clone(), source.format));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the selection of this config for likely mistakes, such as
+    /// patterns that are both included and excluded. Unlike `validate`, these
+    /// are only warnings, and do not stop the config from being used.
+    /// Meta file names that are excluded automatically are not reported as
+    /// useless excludes, even if the includes never match them.
+    pub fn lint(&self) -> Vec<SelectionLint> {
+        let is_source_name = |pattern: &str| {
+            self.sourcer.as_sources().iter().chain(self.sourcer.index()).any(|source| source.name == pattern)
+        };
+
+        self.selection
+            .lint()
+            .into_iter()
+            .filter(|lint| !matches!(lint, SelectionLint::UselessExclude(_, pattern) if is_source_name(pattern)))
+            .collect()
     }
 }
 
@@ -131,9 +226,169 @@ impl Config {
 mod tests {
     use super::*;
 
+    use crate::config::selection::FileOrDir;
     use crate::config::sorter::sort_by::SortBy;
 
     use str_macro::str;
+    use tempfile::TempDir;
+
+    #[test]
+    fn from_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+
+        let yaml_path = path.join("config.yml");
+        std::fs::write(&yaml_path, r#"
+            filtering:
+              include_files: ["*.flac", "*.mp3"]
+              exclude_files: "*.mp3"
+            ordering:
+              sort_by: mod_time
+            sourcing:
+              track: [item.yml]
+              album: [self.yml]
+              index: index.yml
+        "#).unwrap();
+
+        let json_path = path.join("config.JSON");
+        std::fs::write(&json_path, r#"{
+            "filtering": {"include_files": ["*.flac", "*.mp3"], "exclude_files": "*.mp3"},
+            "ordering": {"sort_by": "mod_time"},
+            "sourcing": {"track": ["item.yml"], "album": ["self.yml"], "index": "index.yml"}
+        }"#).unwrap();
+
+        let toml_path = path.join("config.toml");
+        std::fs::write(&toml_path, r#"
+            [filtering]
+            include_files = ["*.flac", "*.mp3"]
+            exclude_files = "*.mp3"
+            [ordering]
+            sort_by = "mod_time"
+            [sourcing]
+            track = ["item.yml"]
+            album = ["self.yml"]
+            index = "index.yml"
+        "#).unwrap();
+
+        for config_path in &[yaml_path, json_path, toml_path] {
+            let config = Config::from_file(config_path).unwrap();
+
+            assert!(config.selection.is_file_pattern_match(&"music.flac"));
+            assert!(!config.selection.is_file_pattern_match(&"music.mp3"));
+            assert!(!config.selection.is_file_pattern_match(&"item.yml"));
+            assert_eq!(config.sorter.sort_by, SortBy::ModTime);
+            assert_eq!(
+                config.sourcer.as_sources(),
+                vec![
+                    Source::from_name(str!("item.yml"), Anchor::External).unwrap(),
+                    Source::from_name(str!("self.yml"), Anchor::Internal).unwrap(),
+                ]
+            );
+            assert_eq!(config.sourcer.index(), Some(&Source::from_name(str!("index.yml"), Anchor::External).unwrap()));
+        }
+
+        // Each kind of failure is reported separately.
+        let produced = Config::from_file(path.join("missing.yml"));
+        assert!(matches!(produced, Err(LoadError::CannotReadFile(..))));
+
+        let unknown_path = path.join("config.ini");
+        std::fs::write(&unknown_path, "").unwrap();
+        let produced = Config::from_file(&unknown_path);
+        assert!(matches!(produced, Err(LoadError::UnknownExtension(p)) if p == unknown_path));
+
+        let produced = Config::from_file(path);
+        assert!(matches!(produced, Err(LoadError::UnknownExtension(..))));
+
+        let invalid_path = path.join("invalid.json");
+        std::fs::write(&invalid_path, r#"{"filtering": {"unknown_field": 1}}"#).unwrap();
+        let produced = Config::from_file(&invalid_path);
+        assert!(matches!(produced, Err(LoadError::CannotParse(FormatError::JsonDeserialize(..)))));
+
+        let invalid_path = path.join("invalid.yaml");
+        std::fs::write(&invalid_path, "filtering: [unclosed").unwrap();
+        let produced = Config::from_file(&invalid_path);
+        assert!(matches!(produced, Err(LoadError::CannotParse(FormatError::YamlDeserialize(..)))));
+    }
+
+    #[test]
+    fn lint() {
+        assert_eq!(Config::default().lint(), vec![]);
+
+        let config = Config::from_str(r#"
+            [filtering]
+            include_files = ["*.flac"]
+            exclude_files = ["*.flac"]
+            [sourcing]
+            track = ["item.yml"]
+            album = ["self.yml"]
+        "#).unwrap();
+        assert_eq!(
+            config.lint(),
+            vec![SelectionLint::OverlappingPattern(FileOrDir::File, str!("*.flac"))],
+        );
+
+        // Meta file names are excluded automatically, and are not reported.
+        let config = Config::from_str(r#"
+            [filtering]
+            include_files = ["*.flac"]
+            [sourcing]
+            track = ["item.yml"]
+            album = ["self.yml"]
+        "#).unwrap();
+        assert_eq!(config.lint(), vec![]);
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(Config::default().validate(), Ok(()));
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.yml", "item.json"]
+            album = ["self.yml"]
+            index = "index.yml"
+        "#).unwrap();
+        assert_eq!(config.validate(), Ok(()));
+
+        // The same name for item and self metadata is ambiguous.
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["meta.yml"]
+            album = ["meta.yml"]
+        "#).unwrap();
+        assert_eq!(config.validate(), Err(ValidationError::DuplicateSourceName(str!("meta.yml"))));
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.yml"]
+            album = ["self.yml"]
+            index = "item.yml"
+        "#).unwrap();
+        assert_eq!(config.validate(), Err(ValidationError::DuplicateSourceName(str!("item.yml"))));
+
+        // Sources can only be made with empty names or mismatched formats by
+        // bypassing `Source::from_name`.
+        let with_source = |source| Config { sourcer: Sourcer::from(vec![source]), ..Config::default() };
+
+        let config = with_source(Source { name: String::new(), anchor: Anchor::External, format: Format::Json });
+        assert_eq!(config.validate(), Err(ValidationError::EmptySourceName));
+
+        let config = with_source(Source { name: str!("item.json"), anchor: Anchor::External, format: Format::Yaml });
+        assert_eq!(config.validate(), Err(ValidationError::FormatMismatch(str!("item.json"), Format::Yaml)));
+
+        // Loading a config file validates it.
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yml");
+
+        std::fs::write(&config_path, "sourcing: {track: [meta.yml], album: [meta.yml]}").unwrap();
+        let produced = Config::from_file(&config_path);
+        assert!(matches!(produced, Err(LoadError::Invalid(ValidationError::DuplicateSourceName(..)))));
+
+        // Empty names are already rejected while parsing.
+        std::fs::write(&config_path, "sourcing: {track: [''], album: [self.yml]}").unwrap();
+        let produced = Config::from_file(&config_path);
+        assert!(matches!(produced, Err(LoadError::CannotParse(..))));
+    }
 
     #[test]
     fn deserialization() {
@@ -225,5 +480,31 @@ mod tests {
                 Source::from_name(str!("album.json"), Anchor::Internal).unwrap(),
             ]
         );
+        assert_eq!(config.sourcer.index(), None);
+
+        let text_config = r#"
+            [sourcing]
+            index = "index.yml"
+        "#;
+
+        let config: Config = toml::from_str(text_config).unwrap();
+
+        assert_eq!(
+            config.sourcer.index(),
+            Some(&Source::from_name(str!("index.yml"), Anchor::External).unwrap()),
+        );
+        assert!(!config.selection.is_file_pattern_match(&"index.yml"));
+
+        // Both spellings of the YAML extension are recognized.
+        let text_config = r#"
+            [sourcing]
+            track = ["item.yaml"]
+            album = ["self.yml"]
+        "#;
+
+        let config: Config = toml::from_str(text_config).unwrap();
+
+        let formats = config.sourcer.as_sources().iter().map(|s| s.format).collect::<Vec<_>>();
+        assert_eq!(formats, vec![Format::Yaml, Format::Yaml]);
     }
 }
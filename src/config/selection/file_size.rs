@@ -0,0 +1,143 @@
+use std::convert::TryFrom;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub(crate) enum FileSizeError {
+    #[error("invalid file size: {0}")]
+    Invalid(String),
+    #[error("unknown file size unit: {0}")]
+    UnknownUnit(String),
+    #[error("file size is not a whole number of bytes: {0}")]
+    Fractional(String),
+    #[error("file size is too large: {0}")]
+    Overflow(String),
+}
+
+/// Returns the number of bytes in a file size unit, ignoring letter case.
+/// Units with a `B` suffix are decimal, and units with an `iB` suffix are binary.
+fn unit_multiplier(unit: &str) -> Option<u128> {
+    let m = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+
+    Some(m)
+}
+
+/// Parses a human-readable file size, such as `1KiB`, `2.5MB`, or `512`, into
+/// a number of bytes. Whitespace between the number and the unit is allowed.
+/// Fractional sizes must work out to a whole number of bytes.
+pub(crate) fn parse_file_size(s: &str) -> Result<u64, FileSizeError> {
+    let s = s.trim();
+    let invalid = || FileSizeError::Invalid(s.to_string());
+
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let multiplier = unit_multiplier(unit.trim())
+        .ok_or_else(|| FileSizeError::UnknownUnit(unit.trim().to_string()))?;
+
+    let (int_digits, frac_digits) = match number.find('.') {
+        Some(i) => (&number[..i], &number[i + 1..]),
+        None => (number, ""),
+    };
+
+    if int_digits.is_empty() && frac_digits.is_empty() || frac_digits.contains('.') {
+        return Err(invalid());
+    }
+
+    let parse_digits = |digits: &str| -> Result<u128, FileSizeError> {
+        if digits.is_empty() { Ok(0) } else { digits.parse().map_err(|_| invalid()) }
+    };
+
+    let int_part = parse_digits(int_digits)?;
+    let frac_part = parse_digits(frac_digits)?;
+
+    let overflow = || FileSizeError::Overflow(s.to_string());
+
+    let frac_scale = u32::try_from(frac_digits.len()).ok()
+        .and_then(|len| 10u128.checked_pow(len))
+        .ok_or_else(invalid)?;
+    let frac_bytes = frac_part.checked_mul(multiplier).ok_or_else(overflow)?;
+
+    if frac_bytes % frac_scale != 0 {
+        return Err(FileSizeError::Fractional(s.to_string()));
+    }
+
+    let bytes = int_part.checked_mul(multiplier)
+        .and_then(|b| b.checked_add(frac_bytes / frac_scale))
+        .ok_or_else(overflow)?;
+
+    u64::try_from(bytes).map_err(|_| overflow())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileSizeInput {
+    Bytes(u64),
+    Text(String),
+}
+
+/// A file size in bytes, deserialized from either a plain integer or a
+/// human-readable string.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "FileSizeInput")]
+pub(crate) struct FileSizeRepr(pub u64);
+
+impl TryFrom<FileSizeInput> for FileSizeRepr {
+    type Error = FileSizeError;
+
+    fn try_from(value: FileSizeInput) -> Result<Self, Self::Error> {
+        match value {
+            FileSizeInput::Bytes(b) => Ok(Self(b)),
+            FileSizeInput::Text(s) => parse_file_size(&s).map(Self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use str_macro::str;
+
+    #[test]
+    fn parse_file_size() {
+        assert_eq!(super::parse_file_size("0"), Ok(0));
+        assert_eq!(super::parse_file_size("512"), Ok(512));
+        assert_eq!(super::parse_file_size("512B"), Ok(512));
+        assert_eq!(super::parse_file_size("1KiB"), Ok(1024));
+        assert_eq!(super::parse_file_size("1kb"), Ok(1000));
+        assert_eq!(super::parse_file_size("2.5MB"), Ok(2_500_000));
+        assert_eq!(super::parse_file_size("700 MiB"), Ok(700 * 1024 * 1024));
+        assert_eq!(super::parse_file_size(" .5GiB "), Ok(512 * 1024 * 1024));
+        assert_eq!(super::parse_file_size("1.TB"), Ok(1_000_000_000_000));
+
+        assert_eq!(super::parse_file_size(""), Err(FileSizeError::Invalid(str!(""))));
+        assert_eq!(super::parse_file_size("MiB"), Err(FileSizeError::Invalid(str!("MiB"))));
+        assert_eq!(super::parse_file_size("1.2.3KB"), Err(FileSizeError::Invalid(str!("1.2.3KB"))));
+        assert_eq!(super::parse_file_size("1 parsec"), Err(FileSizeError::UnknownUnit(str!("parsec"))));
+        assert_eq!(super::parse_file_size("-1KB"), Err(FileSizeError::UnknownUnit(str!("-1KB"))));
+        assert_eq!(super::parse_file_size("1.5B"), Err(FileSizeError::Fractional(str!("1.5B"))));
+        assert_eq!(super::parse_file_size("0.0001KB"), Err(FileSizeError::Fractional(str!("0.0001KB"))));
+        assert_eq!(super::parse_file_size("20000000TB"), Err(FileSizeError::Overflow(str!("20000000TB"))));
+    }
+
+    #[test]
+    fn deserialization() {
+        assert_eq!(serde_yaml::from_str::<FileSizeRepr>("1MiB").unwrap(), FileSizeRepr(1 << 20));
+        assert_eq!(serde_yaml::from_str::<FileSizeRepr>("1024").unwrap(), FileSizeRepr(1024));
+        assert!(serde_yaml::from_str::<FileSizeRepr>("1 parsec").is_err());
+        assert!(serde_yaml::from_str::<FileSizeRepr>("-1").is_err());
+    }
+}
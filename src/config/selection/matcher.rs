@@ -3,16 +3,30 @@ use std::path::Path;
 
 use globset::Error as GlobError;
 use globset::Glob;
+use globset::GlobBuilder;
 use globset::GlobSet;
 use globset::GlobSetBuilder;
+use regex::Error as RegexError;
+use regex::bytes::RegexSet;
+use regex::bytes::RegexSetBuilder;
 use serde::Deserialize;
 use thiserror::Error;
 
 use crate::util::ooms::Ooms;
 
 #[derive(Error, Debug)]
-#[error("invalid pattern: {0}")]
-pub struct PatternError(#[from] GlobError);
+pub enum PatternError {
+    #[error("invalid pattern: {0}")]
+    Glob(#[from] GlobError),
+    #[error("invalid pattern: unbalanced braces in {0:?}")]
+    UnbalancedBraces(String),
+    #[error("invalid regex pattern: {0}")]
+    Regex(#[from] RegexError),
+    #[error("undefined environment variable in pattern: {0}")]
+    UndefinedVariable(String),
+    #[error("invalid pattern: malformed variable reference in {0:?}")]
+    MalformedVariable(String),
+}
 
 #[derive(Error, Debug)]
 #[error("cannot build matcher: {0}")]
@@ -26,32 +40,268 @@ pub enum Error {
     Build(#[from] BuildError),
 }
 
+/// Returns the byte positions of the unescaped braces and commas in a glob
+/// pattern. Character classes are skipped over, since braces and commas are
+/// literal inside of them.
+fn brace_tokens(pattern: &str) -> Vec<(usize, char)> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => {
+                // A closing bracket at the start of a class is literal.
+                chars.next_if(|&(_, c)| c == '!');
+                chars.next_if(|&(_, c)| c == ']');
+
+                for (_, c) in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            '{' | '}' | ',' => tokens.push((i, c)),
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+/// Expands the brace alternates in a glob pattern, returning one pattern for
+/// each combination of alternates, e.g. `*.{flac,wav}` becomes `*.flac` and
+/// `*.wav`. Nested alternates are supported, and escaped braces are kept as-is.
+fn expand_braces(pattern: &str) -> Result<Vec<String>, PatternError> {
+    let unbalanced = || PatternError::UnbalancedBraces(pattern.to_string());
+
+    let mut depth = 0;
+    let mut bounds = Vec::new();
+
+    for (i, c) in brace_tokens(pattern) {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    bounds.push(i);
+                }
+                depth += 1;
+            }
+            '}' => {
+                if depth == 0 {
+                    return Err(unbalanced());
+                }
+                depth -= 1;
+
+                if depth == 0 {
+                    // Expand each alternate of this group along with the rest
+                    // of the pattern, which handles both nested and later groups.
+                    bounds.push(i);
+
+                    let prefix = &pattern[..bounds[0]];
+                    let suffix = &pattern[i + 1..];
+
+                    let mut expanded = Vec::new();
+
+                    for window in bounds.windows(2) {
+                        let alternate = &pattern[window[0] + 1..window[1]];
+                        expanded.extend(expand_braces(&format!("{}{}{}", prefix, alternate, suffix))?);
+                    }
+
+                    return Ok(expanded);
+                }
+            }
+            ',' if depth == 1 => bounds.push(i),
+            _ => {}
+        }
+    }
+
+    if depth > 0 {
+        return Err(unbalanced());
+    }
+
+    Ok(vec![pattern.to_string()])
+}
+
+/// Expands the `$VAR` and `${VAR}` references in a glob pattern, using a
+/// lookup function to get the value of each variable. Variable names consist
+/// of ASCII letters, digits, and underscores, and may not start with a digit.
+/// A `$` that is not followed by a variable name is kept as-is, and escaped
+/// dollar signs (`\$`) are not expanded. Values are inserted verbatim, so any
+/// glob syntax that they contain is interpreted as such.
+fn interpolate_vars<F>(pattern: &str, lookup: F) -> Result<String, PatternError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let is_name_start = |c: char| c.is_ascii_alphabetic() || c == '_';
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut interpolated = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                // Keep escapes intact, they are handled when compiling the glob.
+                interpolated.push(c);
+                interpolated.extend(chars.next());
+            },
+            '$' => {
+                let name = if chars.next_if_eq(&'{').is_some() {
+                    let mut name = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) if is_name_char(c) => name.push(c),
+                            _ => return Err(PatternError::MalformedVariable(pattern.to_string())),
+                        }
+                    }
+
+                    if !name.starts_with(is_name_start) {
+                        return Err(PatternError::MalformedVariable(pattern.to_string()));
+                    }
+
+                    name
+                } else if chars.peek().copied().is_some_and(is_name_start) {
+                    let mut name = String::new();
+
+                    while let Some(c) = chars.next_if(|&c| is_name_char(c)) {
+                        name.push(c);
+                    }
+
+                    name
+                } else {
+                    interpolated.push(c);
+                    continue;
+                };
+
+                let value = lookup(&name).ok_or(PatternError::UndefinedVariable(name))?;
+                interpolated.push_str(&value);
+            },
+            _ => interpolated.push(c),
+        }
+    }
+
+    Ok(interpolated)
+}
+
+/// Options that control how the patterns of a `Matcher` are interpreted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MatcherOpts {
+    /// If true, patterns match file names regardless of letter case.
+    pub case_insensitive: bool,
+}
+
+impl MatcherOpts {
+    fn new_glob(&self, pattern: &str) -> Result<Glob, GlobError> {
+        GlobBuilder::new(pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()
+    }
+}
+
 #[derive(Debug)]
-pub(crate) struct MatcherBuilder(GlobSetBuilder);
+pub(crate) struct MatcherBuilder {
+    globs: Vec<Glob>,
+    patterns: Vec<String>,
+    pattern_indices: Vec<usize>,
+    is_any: bool,
+}
 
 impl MatcherBuilder {
     pub fn new() -> Self {
-        Self(GlobSetBuilder::new())
+        Self {
+            globs: Vec::new(),
+            patterns: Vec::new(),
+            pattern_indices: Vec::new(),
+            is_any: false,
+        }
     }
 
     pub fn add_pattern<S: AsRef<str>>(&mut self, pattern: &S) -> Result<(), PatternError> {
-        self.add_glob(Glob::new(pattern.as_ref())?);
-        Ok(())
+        self.add_pattern_with_opts(pattern, MatcherOpts::default())
     }
 
-    pub fn add_glob(&mut self, glob: Glob) {
-        self.0.add(glob);
+    pub fn add_pattern_with_opts<S: AsRef<str>>(&mut self, pattern: &S, opts: MatcherOpts) -> Result<(), PatternError> {
+        let pattern = pattern.as_ref();
+
+        // Patterns with alternates are split into multiple globs, each of
+        // which remembers the pattern it came from.
+        for expanded in expand_braces(pattern)? {
+            self.globs.push(opts.new_glob(&expanded)?);
+            self.pattern_indices.push(self.patterns.len());
+
+            // A lone star matches every file name, since only file names are
+            // matched against.
+            self.is_any |= matches!(expanded.as_str(), "*" | "**");
+        }
+
+        self.patterns.push(pattern.to_string());
+        Ok(())
     }
 
     pub fn build(self) -> Result<Matcher, BuildError> {
-        Ok(Matcher(self.0.build()?))
+        let mut glob_set_builder = GlobSetBuilder::new();
+
+        for glob in &self.globs {
+            glob_set_builder.add(glob.clone());
+        }
+
+        Ok(Matcher {
+            backend: Backend::Glob(glob_set_builder.build()?),
+            globs: self.globs,
+            patterns: self.patterns,
+            pattern_indices: self.pattern_indices,
+            is_any: self.is_any,
+            combination: None,
+        })
+    }
+
+    /// Builds a matcher after re-interpreting all of the added patterns
+    /// using the given options.
+    pub fn build_with_opts(self, opts: MatcherOpts) -> Result<Matcher, Error> {
+        let mut builder = Self::new();
+
+        for pattern in self.patterns {
+            builder.add_pattern_with_opts(&pattern, opts)?;
+        }
+
+        Ok(builder.build()?)
     }
 }
 
 /// Filter for file paths that uses zero or more glob patterns to perform matching.
+/// Patterns may contain brace alternates, which are expanded into multiple
+/// globs, e.g. `*.{flac,wav}` matches both `music.flac` and `music.wav`.
 #[derive(Debug, Deserialize)]
 #[serde(try_from = "MatcherRepr")]
-pub struct Matcher(GlobSet);
+pub struct Matcher {
+    backend: Backend,
+    globs: Vec<Glob>,
+    patterns: Vec<String>,
+    // The index of the original pattern for each glob in the glob set.
+    pattern_indices: Vec<usize>,
+    is_any: bool,
+    // Set for matchers that cannot be expressed as a flat set of globs, in
+    // which case the fields above are all empty.
+    combination: Option<Box<Combination>>,
+}
+
+/// The compiled patterns of a `Matcher`.
+#[derive(Debug)]
+enum Backend {
+    Glob(GlobSet),
+    Regex(RegexSet),
+}
+
+/// Two matchers that are combined using `Matcher::union` or `Matcher::intersect`.
+#[derive(Debug)]
+enum Combination {
+    Union(Matcher, Matcher),
+    Intersection(Matcher, Matcher),
+}
 
 impl Matcher {
     /// Attempts to build a matcher out of an iterable of string-likes.
@@ -59,24 +309,129 @@ impl Matcher {
     where
         I: IntoIterator<Item = &'a S>,
         S: AsRef<str>,
+    {
+        Self::build_with_opts(pattern_strs, MatcherOpts::default())
+    }
+
+    /// Attempts to build a case-insensitive matcher out of an iterable of
+    /// string-likes. For example, the pattern `*.flac` matches `music.FLAC`.
+    pub fn build_case_insensitive<'a, I, S>(pattern_strs: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
+    {
+        Self::build_with_opts(pattern_strs, MatcherOpts { case_insensitive: true })
+    }
+
+    /// Attempts to build a matcher out of an iterable of string-likes, using
+    /// the given options to interpret the patterns.
+    pub fn build_with_opts<'a, I, S>(pattern_strs: I, opts: MatcherOpts) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
     {
         let mut builder = MatcherBuilder::new();
 
         for pattern_str in pattern_strs {
-            builder.add_pattern(pattern_str)?;
+            builder.add_pattern_with_opts(pattern_str, opts)?;
         }
 
         Ok(builder.build()?)
     }
 
+    /// Attempts to build a matcher out of an iterable of string-likes, after
+    /// expanding any `$VAR` or `${VAR}` environment variable references in
+    /// them. Referencing an unset variable is an error, and escaped dollar
+    /// signs (`\$`) are matched literally.
+    pub fn build_interpolated<'a, I, S>(pattern_strs: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
+    {
+        let patterns = pattern_strs
+            .into_iter()
+            .map(|p| interpolate_vars(p.as_ref(), |name| std::env::var(name).ok()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::build(&patterns)
+    }
+
+    /// Attempts to build a matcher out of an iterable of regular expressions.
+    /// As with glob patterns, the regular expressions are matched against
+    /// file names only, and are not implicitly anchored, so `^` and `$` should
+    /// be used to match an entire file name.
+    pub fn build_regex<'a, I, S>(pattern_strs: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
+    {
+        Self::build_regex_with_opts(pattern_strs, MatcherOpts::default())
+    }
+
+    fn build_regex_with_opts<'a, I, S>(pattern_strs: I, opts: MatcherOpts) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + 'a,
+    {
+        let patterns = pattern_strs.into_iter().map(|p| p.as_ref().to_string()).collect::<Vec<_>>();
+
+        let regex_set = RegexSetBuilder::new(&patterns)
+            .case_insensitive(opts.case_insensitive)
+            .build()
+            .map_err(PatternError::from)?;
+
+        Ok(Self {
+            backend: Backend::Regex(regex_set),
+            pattern_indices: (0..patterns.len()).collect(),
+            patterns,
+            ..Self::empty()
+        })
+    }
+
     /// Matches a path based on its file name. If the path does not have a file
     /// name (e.g. '/' on Unix systems), returns `false`.
     pub fn is_match<P: AsRef<Path>>(&self, path: &P) -> bool {
-        // Matching on only file name is needed for patterns such as "self*".
-        path.as_ref()
-            .file_name()
-            .map(|f| self.0.is_match(f))
-            .unwrap_or(false)
+        match self.combination.as_deref() {
+            None => {
+                // Matching on only file name is needed for patterns such as "self*".
+                path.as_ref()
+                    .file_name()
+                    .map(|f| self.is_any || match &self.backend {
+                        Backend::Glob(glob_set) => glob_set.is_match(f),
+                        Backend::Regex(regex_set) => regex_set.is_match(f.as_encoded_bytes()),
+                    })
+                    .unwrap_or(false)
+            },
+            Some(Combination::Union(a, b)) => a.is_match(path) || b.is_match(path),
+            Some(Combination::Intersection(a, b)) => a.is_match(path) && b.is_match(path),
+        }
+    }
+
+    /// Returns the first pattern that matches a path based on its file name,
+    /// in the order the patterns were provided. If the path does not have a
+    /// file name (e.g. '/' on Unix systems), returns `None`.
+    /// For an intersection, this is the first matching pattern of the left-hand
+    /// matcher, provided that the right-hand matcher also matches.
+    pub fn matching_pattern<P: AsRef<Path>>(&self, path: &P) -> Option<&str> {
+        match self.combination.as_deref() {
+            None => {},
+            Some(Combination::Union(a, b)) => {
+                return a.matching_pattern(path).or_else(|| b.matching_pattern(path));
+            },
+            Some(Combination::Intersection(a, b)) => {
+                return a.matching_pattern(path).filter(|_| b.is_match(path));
+            },
+        }
+
+        let file_name = path.as_ref().file_name()?;
+
+        // Globs and regexes are in the same order as their originating patterns.
+        let index = match &self.backend {
+            Backend::Glob(glob_set) => glob_set.matches(file_name).into_iter().min()?,
+            Backend::Regex(regex_set) => regex_set.matches(file_name.as_encoded_bytes()).iter().next()?,
+        };
+
+        Some(&self.patterns[self.pattern_indices[index]])
     }
 
     /// Returns a matcher that matches any path that has a file name.
@@ -87,44 +442,167 @@ impl Matcher {
 
     /// Returns a matcher that matches no paths.
     pub fn empty() -> Self {
-        Self(GlobSet::empty())
+        Self {
+            backend: Backend::Glob(GlobSet::empty()),
+            globs: Vec::new(),
+            patterns: Vec::new(),
+            pattern_indices: Vec::new(),
+            is_any: false,
+            combination: None,
+        }
+    }
+
+    /// Returns a matcher that matches a path if either this or another
+    /// matcher matches it.
+    pub fn union(self, other: Matcher) -> Matcher {
+        if !self.is_flat_glob() || !other.is_flat_glob() {
+            return Self {
+                combination: Some(Box::new(Combination::Union(self, other))),
+                ..Self::empty()
+            };
+        }
+
+        // Two flat matchers can be merged into a single glob set.
+        let num_patterns = self.patterns.len();
+
+        let mut builder = MatcherBuilder::new();
+        builder.globs = self.globs;
+        builder.globs.extend(other.globs);
+        builder.patterns = self.patterns;
+        builder.patterns.extend(other.patterns);
+        builder.pattern_indices = self.pattern_indices;
+        builder.pattern_indices.extend(other.pattern_indices.into_iter().map(|i| i + num_patterns));
+        builder.is_any = self.is_any || other.is_any;
+
+        // Assume that globs that were already built together will not fail.
+        builder.build().unwrap()
     }
+
+    /// Returns a matcher that matches a path only if both this and another
+    /// matcher match it.
+    pub fn intersect(self, other: Matcher) -> Matcher {
+        Self {
+            combination: Some(Box::new(Combination::Intersection(self, other))),
+            ..Self::empty()
+        }
+    }
+
+    fn is_flat_glob(&self) -> bool {
+        self.combination.is_none() && matches!(self.backend, Backend::Glob(..))
+    }
+
+    fn collect_patterns<'a>(&'a self, patterns: &mut Vec<&'a str>) {
+        match self.combination.as_deref() {
+            None => patterns.extend(self.patterns.iter().map(String::as_str)),
+            Some(Combination::Union(a, b)) | Some(Combination::Intersection(a, b)) => {
+                a.collect_patterns(patterns);
+                b.collect_patterns(patterns);
+            },
+        }
+    }
+
+    /// Returns the original glob patterns used to build this matcher.
+    /// For matchers combined with `union` or `intersect`, these are the
+    /// patterns of both of the combined matchers.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        let mut patterns = Vec::new();
+        self.collect_patterns(&mut patterns);
+        patterns.into_iter()
+    }
+
+    /// Returns true if this matcher matches any path that has a file name,
+    /// such as one created with `Matcher::any`. This is true if any of the
+    /// patterns is, or expands to, a lone star.
+    pub fn is_any(&self) -> bool {
+        match self.combination.as_deref() {
+            None => self.is_any,
+            Some(Combination::Union(a, b)) => a.is_any() || b.is_any(),
+            Some(Combination::Intersection(a, b)) => a.is_any() && b.is_any(),
+        }
+    }
+
+    /// Returns true if this matcher has no patterns, and thus matches no paths,
+    /// such as one created with `Matcher::empty`.
+    pub fn is_empty(&self) -> bool {
+        match self.combination.as_deref() {
+            None => match &self.backend {
+                Backend::Glob(glob_set) => glob_set.is_empty(),
+                Backend::Regex(regex_set) => regex_set.is_empty(),
+            },
+            Some(Combination::Union(a, b)) => a.is_empty() && b.is_empty(),
+            Some(Combination::Intersection(a, b)) => a.is_empty() || b.is_empty(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegexInput {
+    regex: Ooms,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MatcherInput {
+    Glob(Ooms),
+    Regex(RegexInput),
 }
 
 #[derive(Debug, Deserialize)]
-#[serde(try_from = "Ooms")]
+#[serde(try_from = "MatcherInput")]
 pub(crate) enum MatcherRepr {
     Any,
     Empty,
     Custom(MatcherBuilder),
+    Regex(Vec<String>),
 }
 
 impl MatcherRepr {
     pub fn add_pattern<S: AsRef<str>>(&mut self, pattern: &S) -> Result<(), PatternError> {
-        // Always verify that the pattern is valid.
-        let glob = Glob::new(pattern.as_ref())?;
-        self.add_glob(glob);
-        Ok(())
-    }
-
-    pub fn add_glob(&mut self, glob: Glob) {
         match self {
-            // No-op, all patterns are already included.
-            Self::Any => {}
+            // Always verify that the pattern is valid, but otherwise no-op,
+            // all patterns are already included.
+            Self::Any => MatcherBuilder::new().add_pattern(pattern),
 
             // Redefine as a custom variant.
             Self::Empty => {
                 let mut builder = MatcherBuilder::new();
-                builder.add_glob(glob);
+                builder.add_pattern(pattern)?;
 
                 *self = Self::Custom(builder);
+                Ok(())
             }
 
             // Add the pattern to the existing ones.
-            Self::Custom(ref mut builder) => {
-                builder.add_glob(glob);
+            Self::Custom(ref mut builder) => builder.add_pattern(pattern),
+
+            // Convert the glob pattern into equivalent regexes.
+            Self::Regex(ref mut regex_patterns) => {
+                for expanded in expand_braces(pattern.as_ref())? {
+                    regex_patterns.push(Glob::new(&expanded)?.regex().to_string());
+                }
+
+                Ok(())
             }
-        };
+        }
+    }
+}
+
+impl TryFrom<MatcherInput> for MatcherRepr {
+    type Error = PatternError;
+
+    fn try_from(value: MatcherInput) -> Result<Self, Self::Error> {
+        match value {
+            MatcherInput::Glob(ooms) => ooms.try_into(),
+            MatcherInput::Regex(RegexInput { regex }) => {
+                let regex_patterns = regex.iter().map(String::from).collect::<Vec<_>>();
+
+                // Always verify that the patterns are valid.
+                RegexSet::new(&regex_patterns)?;
+
+                Ok(Self::Regex(regex_patterns))
+            }
+        }
     }
 }
 
@@ -142,14 +620,28 @@ impl TryFrom<Ooms> for MatcherRepr {
     }
 }
 
+impl MatcherRepr {
+    /// Converts this into a `Matcher`, using the given options to interpret
+    /// any custom patterns.
+    pub fn build_with_opts(self, opts: MatcherOpts) -> Result<Matcher, Error> {
+        match self {
+            Self::Any => Ok(Matcher::any()),
+            Self::Empty => Ok(Matcher::empty()),
+            Self::Custom(builder) => builder.build_with_opts(opts),
+            Self::Regex(regex_patterns) => Matcher::build_regex_with_opts(&regex_patterns, opts),
+        }
+    }
+}
+
 impl TryFrom<MatcherRepr> for Matcher {
-    type Error = BuildError;
+    type Error = Error;
 
     fn try_from(value: MatcherRepr) -> Result<Self, Self::Error> {
         match value {
             MatcherRepr::Any => Ok(Matcher::any()),
             MatcherRepr::Empty => Ok(Matcher::empty()),
-            MatcherRepr::Custom(builder) => builder.build(),
+            MatcherRepr::Custom(builder) => Ok(builder.build()?),
+            MatcherRepr::Regex(regex_patterns) => Matcher::build_regex(&regex_patterns),
         }
     }
 }
@@ -159,7 +651,7 @@ impl TryFrom<Ooms> for Matcher {
 
     fn try_from(value: Ooms) -> Result<Self, Self::Error> {
         let mr = TryInto::<MatcherRepr>::try_into(value)?;
-        Ok(mr.try_into()?)
+        mr.try_into()
     }
 }
 
@@ -201,6 +693,9 @@ mod tests {
         assert!(Matcher::build(&["[*]"]).is_ok());
         assert!(Matcher::build(&["[?]"]).is_ok());
         assert!(Matcher::build(&["{*.a,*.b,*.c}"]).is_ok());
+        assert!(Matcher::build(&["{*.a,{*.b,*.c}}"]).is_ok());
+        assert!(Matcher::build(&["\\{*.a"]).is_ok());
+        assert!(Matcher::build(&["[{]*.a"]).is_ok());
 
         // Negative test cases.
         // Invalid double star.
@@ -212,13 +707,121 @@ mod tests {
         // Unclosed alternates.
         assert!(Matcher::build(&["{*.a,*.b,*.c"]).is_err());
         // Unopened alternates.
-        // assert!(Matcher::build(&["*.a,*.b,*.c}"]).is_err());
-        // Nested alternates.
-        assert!(Matcher::build(&["{*.a,{*.b,*.c}}"]).is_err());
+        assert!(Matcher::build(&["*.a,*.b,*.c}"]).is_err());
+        // Unbalanced nested alternates.
+        assert!(Matcher::build(&["{*.a,{*.b,*.c}"]).is_err());
         // Dangling escape.
         assert!(Matcher::build(&["*.a\\"]).is_err());
     }
 
+    #[test]
+    fn expand_braces() {
+        assert_eq!(super::expand_braces("*.flac").unwrap(), vec!["*.flac"]);
+        assert_eq!(super::expand_braces("*.{flac,wav}").unwrap(), vec!["*.flac", "*.wav"]);
+        assert_eq!(super::expand_braces("{a,b}.{c,d}").unwrap(), vec!["a.c", "a.d", "b.c", "b.d"]);
+        assert_eq!(super::expand_braces("*.{a,{b,c}x}").unwrap(), vec!["*.a", "*.bx", "*.cx"]);
+        assert_eq!(super::expand_braces("{a,}").unwrap(), vec!["a", ""]);
+
+        // Escaped braces and commas, and those in character classes, are literal.
+        assert_eq!(super::expand_braces("\\{a,b\\}").unwrap(), vec!["\\{a,b\\}"]);
+        assert_eq!(super::expand_braces("{a\\,b,c}").unwrap(), vec!["a\\,b", "c"]);
+        assert_eq!(super::expand_braces("[{]{a,b}").unwrap(), vec!["[{]a", "[{]b"]);
+        assert_eq!(super::expand_braces("[]}]").unwrap(), vec!["[]}]"]);
+
+        assert!(matches!(super::expand_braces("{a,b"), Err(PatternError::UnbalancedBraces(_))));
+        assert!(matches!(super::expand_braces("a,b}"), Err(PatternError::UnbalancedBraces(_))));
+        assert!(matches!(super::expand_braces("{a,{b}"), Err(PatternError::UnbalancedBraces(_))));
+        assert!(matches!(super::expand_braces("{a}}"), Err(PatternError::UnbalancedBraces(_))));
+    }
+
+    #[test]
+    fn brace_expansion() {
+        let matcher = Matcher::build(&["*.{flac,wav,mp3}"]).unwrap();
+        assert!(matcher.is_match(&"music.flac"));
+        assert!(matcher.is_match(&"music.wav"));
+        assert!(matcher.is_match(&"music.mp3"));
+        assert!(!matcher.is_match(&"music.ogg"));
+        assert_eq!(vec!["*.{flac,wav,mp3}"], matcher.patterns().collect::<Vec<_>>());
+
+        let matcher = Matcher::build(&["{item,self}.{json,y{a,}ml}"]).unwrap();
+        assert!(matcher.is_match(&"item.json"));
+        assert!(matcher.is_match(&"self.yaml"));
+        assert!(matcher.is_match(&"self.yml"));
+        assert!(!matcher.is_match(&"self.yal"));
+
+        let matcher = Matcher::build(&["\\{a,b\\}"]).unwrap();
+        assert!(matcher.is_match(&"{a,b}"));
+        assert!(!matcher.is_match(&"a"));
+
+        let matcher = Matcher::build(&["{x,*}"]).unwrap();
+        assert!(matcher.is_any());
+
+        let matcher = Matcher::build_case_insensitive(&["*.{flac,wav}"]).unwrap();
+        assert!(matcher.is_match(&"music.FLAC"));
+        assert!(matcher.is_match(&"music.Wav"));
+
+        let text = "'*.{flac,wav}'";
+        let matcher: Matcher = serde_yaml::from_str(text).unwrap();
+        assert!(matcher.is_match(&"music.flac"));
+        assert!(matcher.is_match(&"music.wav"));
+        assert!(!matcher.is_match(&"music.mp3"));
+
+        let text = "'*.{flac,wav'";
+        assert!(serde_yaml::from_str::<Matcher>(text).is_err());
+    }
+
+    #[test]
+    fn matching_pattern() {
+        let matcher = Matcher::build(&["*.a", "path.*", "*.{b,c}"]).unwrap();
+        assert_eq!(matcher.matching_pattern(&"path.a"), Some("*.a"));
+        assert_eq!(matcher.matching_pattern(&"path.b"), Some("path.*"));
+        assert_eq!(matcher.matching_pattern(&"other.b"), Some("*.{b,c}"));
+        assert_eq!(matcher.matching_pattern(&"extra/other.c"), Some("*.{b,c}"));
+        assert_eq!(matcher.matching_pattern(&"other.d"), None);
+        assert_eq!(matcher.matching_pattern(&"/"), None);
+
+        assert_eq!(Matcher::any().matching_pattern(&"path"), Some("*"));
+        assert_eq!(Matcher::empty().matching_pattern(&"path"), None);
+    }
+
+    #[test]
+    fn patterns() {
+        let matcher = Matcher::build(&["*.a", "*.b"]).unwrap();
+        assert_eq!(vec!["*.a", "*.b"], matcher.patterns().collect::<Vec<_>>());
+
+        assert_eq!(vec!["*"], Matcher::any().patterns().collect::<Vec<_>>());
+        assert_eq!(0, Matcher::empty().patterns().count());
+    }
+
+    #[test]
+    fn is_any_and_is_empty() {
+        let matcher = Matcher::any();
+        assert!(matcher.is_any());
+        assert!(!matcher.is_empty());
+
+        let matcher = Matcher::empty();
+        assert!(!matcher.is_any());
+        assert!(matcher.is_empty());
+
+        let matcher = Matcher::build(&["*.a", "*"]).unwrap();
+        assert!(matcher.is_any());
+        assert!(!matcher.is_empty());
+
+        let matcher = Matcher::build(&["*.a", "*.b"]).unwrap();
+        assert!(!matcher.is_any());
+        assert!(!matcher.is_empty());
+
+        let matcher = Matcher::build(&[] as &[&str]).unwrap();
+        assert!(!matcher.is_any());
+        assert!(matcher.is_empty());
+
+        // The short circuit still requires a file name.
+        let matcher = Matcher::any();
+        assert!(matcher.is_match(&"path.a"));
+        assert!(matcher.is_match(&"path"));
+        assert!(!matcher.is_match(&"/"));
+    }
+
     #[test]
     fn is_match() {
         let matcher = Matcher::build(&["*.a", "*.b"]).unwrap();
@@ -282,6 +885,254 @@ mod tests {
         assert_eq!(matcher.is_match(&""), false);
     }
 
+    #[test]
+    fn is_match_many_patterns() {
+        let patterns = (0..500).map(|i| match i % 5 {
+            0 => format!("track_{}.flac", i),
+            1 => format!("*_{}.mp3", i),
+            2 => format!("disc{}?.wav", i),
+            3 => format!("[ab]{}*.ogg", i),
+            _ => format!("{{x,y}}{}.aac", i),
+        }).collect::<Vec<_>>();
+
+        let matcher = Matcher::build(&patterns).unwrap();
+
+        // Matching each glob in sequence is the reference result.
+        let globs = patterns.iter()
+            .map(|p| Glob::new(p).unwrap().compile_matcher())
+            .collect::<Vec<_>>();
+
+        let mut num_matched = 0;
+
+        for i in 0..600 {
+            let names = vec![
+                format!("track_{}.flac", i),
+                format!("song_{}.mp3", i),
+                format!("disc{}a.wav", i),
+                format!("disc{}.wav", i),
+                format!("a{}b.ogg", i),
+                format!("c{}.ogg", i),
+                format!("y{}.aac", i),
+                format!("z{}.aac", i),
+            ];
+
+            for name in names {
+                let path = Path::new("extra").join(&name);
+                let expected = globs.iter().any(|g| g.is_match(&name));
+
+                assert_eq!(matcher.is_match(&path), expected, "{}", name);
+
+                if expected { num_matched += 1; }
+            }
+        }
+
+        // Make sure that both matching and non-matching names were checked.
+        assert!(num_matched > 0 && num_matched < 600 * 8);
+    }
+
+    #[test]
+    fn union_and_intersect() {
+        let paths = [
+            "music.flac", "music.mp3", "music.wav", "song.flac", "song.mp3", "item.yml", "",
+        ];
+
+        let a = || Matcher::build(&["*.flac", "*.mp3"]).unwrap();
+        let b = || Matcher::build(&["music.*"]).unwrap();
+        let c = || Matcher::build(&["*.{mp3,yml}"]).unwrap();
+
+        for path in &paths {
+            let (in_a, in_b, in_c) = (a().is_match(path), b().is_match(path), c().is_match(path));
+
+            assert_eq!(a().union(b()).is_match(path), in_a || in_b, "{}", path);
+            assert_eq!(a().intersect(b()).is_match(path), in_a && in_b, "{}", path);
+
+            // Distributivity, a.k.a. De Morgan-style combinations.
+            assert_eq!(
+                a().intersect(b().union(c())).is_match(path),
+                a().intersect(b()).union(a().intersect(c())).is_match(path),
+                "{}", path,
+            );
+            assert_eq!(
+                a().union(b().intersect(c())).is_match(path),
+                a().union(b()).intersect(a().union(c())).is_match(path),
+                "{}", path,
+            );
+            assert_eq!(a().union(b()).intersect(c()).is_match(path), (in_a || in_b) && in_c, "{}", path);
+
+            // Any and empty matchers are the identities and annihilators.
+            assert_eq!(Matcher::any().union(a()).is_match(path), !path.is_empty(), "{}", path);
+            assert!(!Matcher::empty().intersect(a()).is_match(path), "{}", path);
+            assert_eq!(Matcher::empty().union(a()).is_match(path), in_a, "{}", path);
+            assert_eq!(Matcher::any().intersect(a()).is_match(path), in_a, "{}", path);
+        }
+
+        assert!(Matcher::any().union(a()).is_any());
+        assert!(a().union(Matcher::any()).is_any());
+        assert!(Matcher::empty().intersect(a()).is_empty());
+        assert!(a().intersect(Matcher::empty()).is_empty());
+        assert!(!a().intersect(b()).is_empty());
+        assert!(!a().intersect(Matcher::any()).is_any());
+
+        // Patterns and matching patterns are kept across combinations.
+        let matcher = a().union(b());
+        assert_eq!(matcher.patterns().collect::<Vec<_>>(), vec!["*.flac", "*.mp3", "music.*"]);
+        assert_eq!(matcher.matching_pattern(&"music.wav"), Some("music.*"));
+
+        let matcher = a().intersect(b()).union(c());
+        assert_eq!(matcher.patterns().collect::<Vec<_>>(), vec!["*.flac", "*.mp3", "music.*", "*.{mp3,yml}"]);
+        assert_eq!(matcher.matching_pattern(&"music.mp3"), Some("*.mp3"));
+        assert_eq!(matcher.matching_pattern(&"song.mp3"), Some("*.{mp3,yml}"));
+        assert_eq!(matcher.matching_pattern(&"music.wav"), None);
+
+        // Case-insensitivity is kept when merging.
+        let matcher = Matcher::build_case_insensitive(&["*.flac"]).unwrap().union(b());
+        assert!(matcher.is_match(&"MUSIC.FLAC"));
+        assert!(!matcher.is_match(&"MUSIC.wav"));
+    }
+
+    #[test]
+    fn interpolate_vars() {
+        let lookup = |name: &str| match name {
+            "EXT" => Some(String::from("flac")),
+            "ROOT_DIR" => Some(String::from("music")),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+
+        let interpolate = |pattern: &str| super::interpolate_vars(pattern, lookup);
+
+        assert_eq!(interpolate("*.flac").unwrap(), "*.flac");
+        assert_eq!(interpolate("*.$EXT").unwrap(), "*.flac");
+        assert_eq!(interpolate("*.${EXT}").unwrap(), "*.flac");
+        assert_eq!(interpolate("$ROOT_DIR/**").unwrap(), "music/**");
+        assert_eq!(interpolate("${ROOT_DIR}_old.$EXT").unwrap(), "music_old.flac");
+        assert_eq!(interpolate("a$EMPTY.b").unwrap(), "a.b");
+
+        // Escaped and bare dollar signs are kept as-is.
+        assert_eq!(interpolate("\\$EXT").unwrap(), "\\$EXT");
+        assert_eq!(interpolate("cost_$").unwrap(), "cost_$");
+        assert_eq!(interpolate("$5.flac").unwrap(), "$5.flac");
+
+        assert!(matches!(interpolate("*.$MISSING"), Err(PatternError::UndefinedVariable(n)) if n == "MISSING"));
+        assert!(matches!(interpolate("*.${MISSING}"), Err(PatternError::UndefinedVariable(n)) if n == "MISSING"));
+        assert!(matches!(interpolate("*.${EXT"), Err(PatternError::MalformedVariable(..))));
+        assert!(matches!(interpolate("*.${}"), Err(PatternError::MalformedVariable(..))));
+        assert!(matches!(interpolate("*.${1X}"), Err(PatternError::MalformedVariable(..))));
+    }
+
+    #[test]
+    fn build_interpolated() {
+        std::env::set_var("ANAGMA_TEST_BUILD_INTERPOLATED_EXT", "flac");
+        std::env::remove_var("ANAGMA_TEST_BUILD_INTERPOLATED_UNSET");
+
+        let matcher = Matcher::build_interpolated(&["*.$ANAGMA_TEST_BUILD_INTERPOLATED_EXT"]).unwrap();
+        assert!(matcher.is_match(&"music.flac"));
+        assert!(!matcher.is_match(&"music.wav"));
+        assert_eq!(matcher.patterns().collect::<Vec<_>>(), vec!["*.flac"]);
+
+        let matcher = Matcher::build_interpolated(&["{a,b}.${ANAGMA_TEST_BUILD_INTERPOLATED_EXT}"]).unwrap();
+        assert!(matcher.is_match(&"a.flac"));
+        assert!(matcher.is_match(&"b.flac"));
+
+        // Escaped dollar signs are matched literally.
+        let matcher = Matcher::build_interpolated(&["\\$ANAGMA_TEST_BUILD_INTERPOLATED_EXT"]).unwrap();
+        assert!(matcher.is_match(&"$ANAGMA_TEST_BUILD_INTERPOLATED_EXT"));
+        assert!(!matcher.is_match(&"flac"));
+
+        let produced = Matcher::build_interpolated(&["*.$ANAGMA_TEST_BUILD_INTERPOLATED_UNSET"]);
+        assert!(matches!(
+            produced,
+            Err(Error::Pattern(PatternError::UndefinedVariable(n))) if n == "ANAGMA_TEST_BUILD_INTERPOLATED_UNSET"
+        ));
+    }
+
+    #[test]
+    fn build_regex() {
+        let matcher = Matcher::build_regex(&[r"^\d{4}\.flac$"]).unwrap();
+        assert!(matcher.is_match(&"2021.flac"));
+        assert!(matcher.is_match(&"path/to/2021.flac"));
+        assert!(!matcher.is_match(&"21.flac"));
+        assert!(!matcher.is_match(&"20211.flac"));
+        assert!(!matcher.is_match(&"2021.mp3"));
+        assert!(!matcher.is_match(&"/"));
+        assert_eq!(matcher.matching_pattern(&"2021.flac"), Some(r"^\d{4}\.flac$"));
+        assert!(!matcher.is_any());
+        assert!(!matcher.is_empty());
+
+        assert!(Matcher::build_regex(&[] as &[&str]).unwrap().is_empty());
+        assert!(Matcher::build_regex(&["^(unclosed"]).is_err());
+
+        // Regex matchers can be combined with glob matchers.
+        let matcher = Matcher::build(&["*.mp3"]).unwrap().union(Matcher::build_regex(&[r"^\d+\.flac$"]).unwrap());
+        assert!(matcher.is_match(&"song.mp3"));
+        assert!(matcher.is_match(&"21.flac"));
+        assert!(!matcher.is_match(&"song.flac"));
+
+        // File names that are not valid UTF-8 are matched on their raw bytes.
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+
+            let file_name = OsStr::from_bytes(b"\xFFtrack.flac");
+            let matcher = Matcher::build_regex(&[r"^(?-u:\xFF)track\.flac$"]).unwrap();
+            assert!(matcher.is_match(&file_name));
+            assert_eq!(matcher.matching_pattern(&file_name), Some(r"^(?-u:\xFF)track\.flac$"));
+
+            let matcher = Matcher::build_regex(&[r"^\x{FFFD}"]).unwrap();
+            assert!(!matcher.is_match(&file_name));
+        }
+
+        // Regexes are opted into with a tagged map.
+        let matcher: Matcher = serde_yaml::from_str(r#"{ regex: '^\d{4}\.flac$' }"#).unwrap();
+        assert!(matcher.is_match(&"2021.flac"));
+        assert!(!matcher.is_match(&"21.flac"));
+
+        let matcher: Matcher = serde_yaml::from_str(r#"{ regex: ['^a', 'b$'] }"#).unwrap();
+        assert!(matcher.is_match(&"apple"));
+        assert!(matcher.is_match(&"bob"));
+        assert!(!matcher.is_match(&"bobs"));
+
+        assert!(serde_yaml::from_str::<Matcher>(r#"{ regex: '^(unclosed' }"#).is_err());
+        assert!(serde_yaml::from_str::<Matcher>(r#"{ regexes: '^a' }"#).is_err());
+
+        // Glob patterns added to a regex repr are converted into regexes.
+        let mut repr: MatcherRepr = serde_yaml::from_str(r#"{ regex: '^\d+$' }"#).unwrap();
+        repr.add_pattern(&"self.{yml,json}").unwrap();
+        let matcher = repr.build_with_opts(MatcherOpts { case_insensitive: true }).unwrap();
+        assert!(matcher.is_match(&"123"));
+        assert!(matcher.is_match(&"self.yml"));
+        assert!(matcher.is_match(&"SELF.JSON"));
+        assert!(!matcher.is_match(&"self.toml"));
+    }
+
+    #[test]
+    fn build_case_insensitive() {
+        let matcher = Matcher::build(&["*.flac"]).unwrap();
+        assert!(matcher.is_match(&"music.flac"));
+        assert!(!matcher.is_match(&"music.FLAC"));
+
+        let matcher = Matcher::build_case_insensitive(&["*.flac"]).unwrap();
+        assert!(matcher.is_match(&"music.flac"));
+        assert!(matcher.is_match(&"music.FLAC"));
+        assert!(matcher.is_match(&"MUSIC.Flac"));
+        assert!(!matcher.is_match(&"music.mp3"));
+
+        let matcher = Matcher::build_case_insensitive(&["*.FLAC"]).unwrap();
+        assert!(matcher.is_match(&"music.flac"));
+        assert!(matcher.is_match(&"music.FLAC"));
+
+        // Invalid patterns are still rejected.
+        assert!(Matcher::build_case_insensitive(&["[abc"]).is_err());
+
+        // Options can also be applied when converting from a repr.
+        let mut repr = MatcherRepr::Empty;
+        repr.add_pattern(&"*.flac").unwrap();
+        let matcher = repr.build_with_opts(MatcherOpts { case_insensitive: true }).unwrap();
+        assert!(matcher.is_match(&"music.FLAC"));
+        assert_eq!(vec!["*.flac"], matcher.patterns().collect::<Vec<_>>());
+    }
+
     #[test]
     fn any() {
         let matcher = Matcher::any();
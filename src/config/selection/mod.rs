@@ -1,8 +1,11 @@
+mod file_size;
 mod matcher;
 
-use std::convert::{TryFrom, TryInto};
-use std::fs::ReadDir;
-use std::io::Result as IoResult;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use std::fs::{FileType, ReadDir};
+use std::io::{Error as IoError, Result as IoResult};
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -10,14 +13,56 @@ use serde::Deserialize;
 
 use crate::config::Sorter;
 
-pub use self::matcher::{Error as MatcherError, Matcher};
+pub use self::matcher::{Error as MatcherError, Matcher, MatcherOpts};
 pub(crate) use self::matcher::MatcherRepr;
+pub(crate) use self::file_size::FileSizeRepr;
 
-enum FileOrDir {
+/// Distinguishes between the file and directory halves of a `Selection`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FileOrDir {
     File,
     Dir,
 }
 
+/// A likely mistake in the patterns of a `Selection`, as reported by
+/// `Selection::lint`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SelectionLint {
+    /// The same pattern is both included and excluded, so it selects nothing.
+    OverlappingPattern(FileOrDir, String),
+    /// A literal exclude pattern is never matched by the include patterns, so
+    /// it has no effect.
+    UselessExclude(FileOrDir, String),
+    /// There are no include patterns, so nothing will be selected.
+    EmptyInclude(FileOrDir),
+}
+
+/// Describes which patterns of a `Selection` matched a path, as reported by
+/// `Selection::explain`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SelectionExplanation {
+    /// Whether the path was checked against the file or directory patterns.
+    pub file_or_dir: FileOrDir,
+    /// The first include pattern that matched the path, if any.
+    pub include: Option<String>,
+    /// The first exclude pattern that matched the path, if any.
+    pub exclude: Option<String>,
+}
+
+impl SelectionExplanation {
+    /// Returns true if the path was selected, meaning that an include pattern
+    /// matched, and no exclude pattern vetoed it.
+    pub fn is_selected(&self) -> bool {
+        self.include.is_some() && self.exclude.is_none()
+    }
+}
+
+/// Returns true if a glob pattern has no special characters, and can only
+/// match a file name that is exactly equal to it.
+fn is_literal_pattern(pattern: &str) -> bool {
+    !pattern.contains(&['*', '?', '[', ']', '{', '}', '\\'][..])
+}
+
 /// A type that represents included and excluded item files and directories.
 #[derive(Debug)]
 pub struct Selection {
@@ -25,6 +70,9 @@ pub struct Selection {
     exclude_files: Matcher,
     include_dirs: Matcher,
     exclude_dirs: Matcher,
+    skip_dir_if_contains: Vec<String>,
+    min_file_size: Option<u64>,
+    max_file_size: Option<u64>,
 }
 
 impl Default for Selection {
@@ -50,9 +98,47 @@ impl Selection {
             exclude_files,
             include_dirs,
             exclude_dirs,
+            skip_dir_if_contains: Vec::new(),
+            min_file_size: None,
+            max_file_size: None,
         }
     }
 
+    /// Sets the names of marker files that cause a directory to be skipped.
+    /// Any directory that directly contains a file with one of these names is
+    /// not selected, which prunes its entire subtree when walking.
+    pub fn with_skip_dir_if_contains<I, S>(mut self, marker_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.skip_dir_if_contains = marker_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the inclusive minimum and maximum sizes in bytes of files to be
+    /// selected. Files outside of these bounds are not selected, directories
+    /// are not affected.
+    pub fn with_file_size_bounds(mut self, min_file_size: Option<u64>, max_file_size: Option<u64>) -> Self {
+        self.min_file_size = min_file_size;
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Returns true if a file size is within the file size bounds.
+    fn is_file_size_in_bounds(&self, file_size: u64) -> bool {
+        !matches!(self.min_file_size, Some(min) if file_size < min)
+            && !matches!(self.max_file_size, Some(max) if file_size > max)
+    }
+
+    /// Returns true if a directory contains any of the skip marker files.
+    /// This accesses the filesystem.
+    fn has_skip_marker(&self, dir_path: &Path) -> bool {
+        self.skip_dir_if_contains
+            .iter()
+            .any(|marker_name| dir_path.join(marker_name).is_file())
+    }
+
     pub fn from_patterns<'a, IA, SA, IB, SB, IC, SC, ID, SD>(
         include_file_patterns: IA,
         exclude_file_patterns: IB,
@@ -69,10 +155,63 @@ impl Selection {
         ID: IntoIterator<Item = &'a SD>,
         SD: AsRef<str> + 'a,
     {
-        let include_files = Matcher::build(include_file_patterns)?;
-        let exclude_files = Matcher::build(exclude_file_patterns)?;
-        let include_dirs = Matcher::build(include_dir_patterns)?;
-        let exclude_dirs = Matcher::build(exclude_dir_patterns)?;
+        Self::from_patterns_with_opts(
+            include_file_patterns,
+            exclude_file_patterns,
+            include_dir_patterns,
+            exclude_dir_patterns,
+            MatcherOpts::default(),
+        )
+    }
+
+    /// Same as `from_patterns`, but all patterns match file names regardless
+    /// of letter case.
+    pub fn from_patterns_ci<'a, IA, SA, IB, SB, IC, SC, ID, SD>(
+        include_file_patterns: IA,
+        exclude_file_patterns: IB,
+        include_dir_patterns: IC,
+        exclude_dir_patterns: ID,
+    ) -> Result<Self, MatcherError>
+    where
+        IA: IntoIterator<Item = &'a SA>,
+        SA: AsRef<str> + 'a,
+        IB: IntoIterator<Item = &'a SB>,
+        SB: AsRef<str> + 'a,
+        IC: IntoIterator<Item = &'a SC>,
+        SC: AsRef<str> + 'a,
+        ID: IntoIterator<Item = &'a SD>,
+        SD: AsRef<str> + 'a,
+    {
+        Self::from_patterns_with_opts(
+            include_file_patterns,
+            exclude_file_patterns,
+            include_dir_patterns,
+            exclude_dir_patterns,
+            MatcherOpts { case_insensitive: true },
+        )
+    }
+
+    fn from_patterns_with_opts<'a, IA, SA, IB, SB, IC, SC, ID, SD>(
+        include_file_patterns: IA,
+        exclude_file_patterns: IB,
+        include_dir_patterns: IC,
+        exclude_dir_patterns: ID,
+        opts: MatcherOpts,
+    ) -> Result<Self, MatcherError>
+    where
+        IA: IntoIterator<Item = &'a SA>,
+        SA: AsRef<str> + 'a,
+        IB: IntoIterator<Item = &'a SB>,
+        SB: AsRef<str> + 'a,
+        IC: IntoIterator<Item = &'a SC>,
+        SC: AsRef<str> + 'a,
+        ID: IntoIterator<Item = &'a SD>,
+        SD: AsRef<str> + 'a,
+    {
+        let include_files = Matcher::build_with_opts(include_file_patterns, opts)?;
+        let exclude_files = Matcher::build_with_opts(exclude_file_patterns, opts)?;
+        let include_dirs = Matcher::build_with_opts(include_dir_patterns, opts)?;
+        let exclude_dirs = Matcher::build_with_opts(exclude_dir_patterns, opts)?;
 
         Ok(Self::new(
             include_files,
@@ -82,13 +221,45 @@ impl Selection {
         ))
     }
 
-    fn is_pattern_match<P: AsRef<Path>>(&self, path: &P, fod: FileOrDir) -> bool {
-        let (inc, exc) = match fod {
+    fn matchers(&self, fod: FileOrDir) -> (&Matcher, &Matcher) {
+        match fod {
             FileOrDir::File => (&self.include_files, &self.exclude_files),
             FileOrDir::Dir => (&self.include_dirs, &self.exclude_dirs),
-        };
+        }
+    }
+
+    fn is_pattern_match<P: AsRef<Path>>(&self, path: &P, fod: FileOrDir) -> bool {
+        let (inc, exc) = self.matchers(fod);
+
+        // An empty exclude can never reject a path, so skip evaluating it.
+        inc.is_match(&path) && (exc.is_empty() || !exc.is_match(&path))
+    }
+
+    /// Checks the patterns of this `Selection` for likely mistakes, such as
+    /// patterns that are both included and excluded, literal excludes that the
+    /// includes never match, and empty include sets.
+    /// Only literal exclude patterns are checked for usefulness, since deciding
+    /// whether two arbitrary globs overlap is not supported.
+    pub fn lint(&self) -> Vec<SelectionLint> {
+        let mut lints = Vec::new();
+
+        for &fod in &[FileOrDir::File, FileOrDir::Dir] {
+            let (inc, exc) = self.matchers(fod);
 
-        inc.is_match(&path) && !exc.is_match(&path)
+            if inc.patterns().next().is_none() {
+                lints.push(SelectionLint::EmptyInclude(fod));
+            }
+
+            for exc_pattern in exc.patterns() {
+                if inc.patterns().any(|inc_pattern| inc_pattern == exc_pattern) {
+                    lints.push(SelectionLint::OverlappingPattern(fod, exc_pattern.to_string()));
+                } else if is_literal_pattern(exc_pattern) && !inc.is_match(&exc_pattern) {
+                    lints.push(SelectionLint::UselessExclude(fod, exc_pattern.to_string()));
+                }
+            }
+        }
+
+        lints
     }
 
     /// Returns true if the path matches according to the file matcher.
@@ -109,18 +280,82 @@ impl Selection {
         self.is_pattern_match(path, FileOrDir::Dir)
     }
 
-    /// Returns true if a path is selected.
-    /// This accesses the filesystem to tell if the path is a file or directory.
-    pub fn is_selected<P: AsRef<Path>>(&self, path: &P) -> IoResult<bool> {
-        let file_info = std::fs::metadata(&path)?;
+    /// Explains which patterns of this `Selection` match a path, assuming the
+    /// path is a file or a directory as specified.
+    /// As with `is_file_pattern_match` and `is_dir_pattern_match`, this uses
+    /// only the lexical content of the path, so skip marker files and file
+    /// size bounds are not considered.
+    pub fn explain<P: AsRef<Path>>(&self, path: &P, fod: FileOrDir) -> SelectionExplanation {
+        let (inc, exc) = self.matchers(fod);
 
-        Ok(if file_info.is_file() {
+        SelectionExplanation {
+            file_or_dir: fod,
+            include: inc.matching_pattern(path).map(String::from),
+            exclude: exc.matching_pattern(path).map(String::from),
+        }
+    }
+
+    /// Returns true if a path with an already-known file type is selected.
+    /// Paths that are neither files nor directories are never selected.
+    fn is_selected_file_type<P: AsRef<Path>>(&self, path: &P, file_type: FileType) -> bool {
+        if file_type.is_file() {
             self.is_file_pattern_match(path)
-        } else if file_info.is_dir() {
+        } else if file_type.is_dir() {
             self.is_dir_pattern_match(path)
         } else {
             false
-        })
+        }
+    }
+
+    /// Returns whether a path is a selected file or directory, or `None` if
+    /// the path is not selected.
+    /// This accesses the filesystem to tell if the path is a file or directory,
+    /// to check file sizes, and to check directories for skip marker files.
+    fn selected_kind<P: AsRef<Path>>(&self, path: &P) -> IoResult<Option<FileOrDir>> {
+        let file_info = std::fs::metadata(&path)?;
+
+        if file_info.is_dir() && self.has_skip_marker(path.as_ref()) {
+            return Ok(None);
+        }
+
+        if file_info.is_file() && !self.is_file_size_in_bounds(file_info.len()) {
+            return Ok(None);
+        }
+
+        let fod = if file_info.is_file() {
+            FileOrDir::File
+        } else if file_info.is_dir() {
+            FileOrDir::Dir
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(fod).filter(|&fod| self.is_pattern_match(path, fod)))
+    }
+
+    /// Returns true if a path is selected.
+    /// This accesses the filesystem to tell if the path is a file or directory,
+    /// to check file sizes, and to check directories for skip marker files.
+    pub fn is_selected<P: AsRef<Path>>(&self, path: &P) -> IoResult<bool> {
+        Ok(self.selected_kind(path)?.is_some())
+    }
+
+    /// Filters a pre-listed collection of paths and their file types, keeping
+    /// only the ones that match this `Selection`.
+    /// Note that this does not access the filesystem, the provided file types
+    /// are trusted as-is. Symlink file types are never selected, so callers
+    /// should resolve symlinks beforehand if they are to be followed. Since
+    /// the filesystem is not accessed, directories are not checked for skip
+    /// marker files, and file size bounds are not checked.
+    pub fn filter_entries<I>(&self, entries: I) -> Vec<PathBuf>
+    where
+        I: IntoIterator<Item = (PathBuf, FileType)>,
+    {
+        entries
+            .into_iter()
+            .filter(|(path, file_type)| self.is_selected_file_type(path, *file_type))
+            .map(|(path, _)| path)
+            .collect()
     }
 
     /// Selects paths inside a directory that match this `Selection`.
@@ -131,7 +366,64 @@ impl Selection {
         // Try to open the path as a directory, handle the error as appropriate.
         let dir_reader = dir_path.read_dir()?;
 
-        Ok(SelectedSubPaths(dir_reader, &self))
+        Ok(SelectedSubPaths(dir_reader, &self, None))
+    }
+
+    /// Selects paths inside a directory that match this `Selection`, and splits
+    /// them into selected files and selected directories, in that order.
+    /// Any errors encountered on individual sub-paths are returned separately,
+    /// instead of stopping the selection.
+    /// Each sub-path is only accessed once to classify it, so callers do not
+    /// need to access the filesystem again to tell files and directories apart.
+    pub fn select_in_dir_partitioned(
+        &self,
+        dir_path: &Path,
+    ) -> IoResult<(Vec<PathBuf>, Vec<PathBuf>, Vec<IoError>)> {
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        let mut errs = Vec::new();
+
+        for res in dir_path.read_dir()? {
+            let sub_path = match res {
+                Ok(dir_entry) => dir_entry.path(),
+                Err(err) => {
+                    errs.push(err);
+                    continue;
+                }
+            };
+
+            match self.selected_kind(&sub_path) {
+                Ok(Some(FileOrDir::File)) => files.push(sub_path),
+                Ok(Some(FileOrDir::Dir)) => dirs.push(sub_path),
+                Ok(None) => {}
+                Err(err) => errs.push(err),
+            }
+        }
+
+        Ok((files, dirs, errs))
+    }
+
+    /// Lazily selects file paths inside a directory and its sub-directories
+    /// that match this `Selection`. Only selected directories are descended
+    /// into, down to an optional maximum depth, where a depth of 0 only selects
+    /// the direct children of the root directory.
+    /// For each directory, any errors are yielded first, followed by its sorted
+    /// selected files, and then the files of each of its sorted selected
+    /// sub-directories in turn. Only the listings of the directories currently
+    /// being visited are kept in memory.
+    pub fn select_recursive<'a>(
+        &'a self,
+        root: &Path,
+        max_depth: Option<usize>,
+        sorter: &'a Sorter,
+    ) -> SelectedRecursive<'a> {
+        SelectedRecursive {
+            selection: self,
+            sorter,
+            max_depth,
+            root: Some(root.to_path_buf()),
+            stack: Vec::new(),
+        }
     }
 
     /// Selects paths inside a directory that match this `Selection`, and sorts them.
@@ -146,6 +438,170 @@ impl Selection {
 
         Ok(res_paths)
     }
+
+    /// Similar to `select_in_dir_sorted`, but also returns the paths inside
+    /// the directory that were not selected, in directory listing order.
+    /// Each path is only accessed once, in the same pass as the selection.
+    pub fn select_in_dir_sorted_with_rejected(
+        &self,
+        dir_path: &Path,
+        sorter: &Sorter,
+    ) -> IoResult<(Vec<IoResult<PathBuf>>, Vec<PathBuf>)> {
+        let mut sub_paths = SelectedSubPaths(dir_path.read_dir()?, self, Some(Vec::new()));
+        let mut res_paths = sub_paths.by_ref().collect::<Vec<_>>();
+
+        sorter.sort_path_results(&mut res_paths);
+
+        Ok((res_paths, sub_paths.2.unwrap_or_default()))
+    }
+
+    /// Selects paths inside a directory that match this `Selection`, sorts
+    /// them, and keeps only the first `limit` results. As with
+    /// `select_in_dir_sorted`, any errors are sorted to the front.
+    /// Instead of sorting every selected path, this keeps a bounded heap of
+    /// the best `limit` paths seen so far, which takes `O(n log k)` time and
+    /// `O(k)` space (where `k` is the limit) instead of `O(n log n)` and
+    /// `O(n)` for a full sort. Paths that compare as equal are kept in the
+    /// order they were selected in, as with a full (stable) sort.
+    pub fn select_in_dir_sorted_limited(
+        &self,
+        dir_path: &Path,
+        sorter: &Sorter,
+        limit: usize,
+    ) -> IoResult<Vec<IoResult<PathBuf>>> {
+        // A shuffle cannot be done using a bounded heap.
+        if sorter.is_random() {
+            let mut res_paths = self.select_in_dir_sorted(dir_path, sorter)?;
+            res_paths.truncate(limit);
+            return Ok(res_paths);
+        }
+
+        let mut errs = Vec::new();
+        let mut heap = BinaryHeap::new();
+
+        for (seq_idx, res) in self.select_in_dir(dir_path)?.enumerate() {
+            match res {
+                Err(err) => {
+                    if errs.len() < limit {
+                        errs.push(Err(err));
+                    }
+                }
+                Ok(path) => {
+                    heap.push(SortedEntry(path, seq_idx, sorter));
+
+                    // Drop the entry that would sort last, keeping the heap bounded.
+                    if heap.len() > limit {
+                        heap.pop();
+                    }
+                }
+            }
+        }
+
+        let num_paths = limit - errs.len();
+
+        let mut res_paths = errs;
+        res_paths.extend(
+            heap.into_sorted_vec()
+                .into_iter()
+                .take(num_paths)
+                .map(|SortedEntry(path, _, _)| Ok(path))
+        );
+
+        Ok(res_paths)
+    }
+}
+
+/// Helper for ordering paths in a heap using a `Sorter`.
+/// Ties are broken by the sequence index of each path, so that the heap keeps
+/// the same order as a stable sort.
+struct SortedEntry<'a>(PathBuf, usize, &'a Sorter);
+
+impl<'a> PartialEq for SortedEntry<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for SortedEntry<'a> {}
+
+impl<'a> PartialOrd for SortedEntry<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for SortedEntry<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.2.cmp_paths(&self.0, &other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+/// The pending files and sub-directories of a directory being visited by a
+/// `SelectedRecursive` iterator.
+struct DirFrame {
+    depth: usize,
+    files: std::vec::IntoIter<IoResult<PathBuf>>,
+    sub_dirs: std::vec::IntoIter<PathBuf>,
+}
+
+/// Iterator over the selected file paths in a directory tree, created by
+/// `Selection::select_recursive`.
+pub struct SelectedRecursive<'a> {
+    selection: &'a Selection,
+    sorter: &'a Sorter,
+    max_depth: Option<usize>,
+    root: Option<PathBuf>,
+    stack: Vec<DirFrame>,
+}
+
+impl<'a> SelectedRecursive<'a> {
+    fn visit(&mut self, dir_path: &Path, depth: usize) -> IoResult<()> {
+        let (mut files, mut sub_dirs, errs) = self.selection.select_in_dir_partitioned(dir_path)?;
+
+        self.sorter.sort_paths(&mut files);
+        self.sorter.sort_paths(&mut sub_dirs);
+
+        // Errors are placed first, as with `select_in_dir_sorted`.
+        let files = errs.into_iter().map(Err).chain(files.into_iter().map(Ok)).collect::<Vec<_>>();
+
+        self.stack.push(DirFrame { depth, files: files.into_iter(), sub_dirs: sub_dirs.into_iter() });
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for SelectedRecursive<'a> {
+    type Item = IoResult<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if let Err(err) = self.visit(&root, 0) {
+                return Some(Err(err));
+            }
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+
+            if let Some(res) = frame.files.next() {
+                return Some(res);
+            }
+
+            let depth = frame.depth;
+
+            match frame.sub_dirs.next() {
+                Some(sub_dir) => {
+                    if !matches!(self.max_depth, Some(max_depth) if depth >= max_depth) {
+                        if let Err(err) = self.visit(&sub_dir, depth + 1) {
+                            return Some(Err(err));
+                        }
+                    }
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -156,6 +612,10 @@ pub(crate) struct SelectionRepr {
     pub exclude_files: MatcherRepr,
     pub include_dirs: MatcherRepr,
     pub exclude_dirs: MatcherRepr,
+    pub skip_dir_if_contains: Vec<String>,
+    pub case_insensitive: bool,
+    pub min_file_size: Option<FileSizeRepr>,
+    pub max_file_size: Option<FileSizeRepr>,
 }
 
 impl Default for SelectionRepr {
@@ -166,6 +626,10 @@ impl Default for SelectionRepr {
             exclude_files: MatcherRepr::Empty,
             include_dirs: MatcherRepr::Any,
             exclude_dirs: MatcherRepr::Empty,
+            skip_dir_if_contains: Vec::new(),
+            case_insensitive: false,
+            min_file_size: None,
+            max_file_size: None,
         }
     }
 }
@@ -174,16 +638,24 @@ impl TryFrom<SelectionRepr> for Selection {
     type Error = MatcherError;
 
     fn try_from(value: SelectionRepr) -> Result<Self, Self::Error> {
+        let opts = MatcherOpts { case_insensitive: value.case_insensitive };
+
         Ok(Self {
-            include_files: value.include_files.try_into()?,
-            exclude_files: value.exclude_files.try_into()?,
-            include_dirs: value.include_dirs.try_into()?,
-            exclude_dirs: value.exclude_dirs.try_into()?,
+            include_files: value.include_files.build_with_opts(opts)?,
+            exclude_files: value.exclude_files.build_with_opts(opts)?,
+            include_dirs: value.include_dirs.build_with_opts(opts)?,
+            exclude_dirs: value.exclude_dirs.build_with_opts(opts)?,
+            skip_dir_if_contains: value.skip_dir_if_contains,
+            min_file_size: value.min_file_size.map(|FileSizeRepr(b)| b),
+            max_file_size: value.max_file_size.map(|FileSizeRepr(b)| b),
         })
     }
 }
 
-pub struct SelectedSubPaths<'a>(ReadDir, &'a Selection);
+/// Iterator over the selected paths inside a directory, created by
+/// `Selection::select_in_dir`. Optionally keeps the paths that were not
+/// selected.
+pub struct SelectedSubPaths<'a>(ReadDir, &'a Selection, Option<Vec<PathBuf>>);
 
 impl<'a> Iterator for SelectedSubPaths<'a> {
     type Item = IoResult<PathBuf>;
@@ -192,6 +664,7 @@ impl<'a> Iterator for SelectedSubPaths<'a> {
         // LEARN: Unable to inline these, had to use `let`, why is that?
         let read_dir = &mut self.0;
         let selection = &self.1;
+        let opt_rejected = &mut self.2;
 
         // Get next entry from the directory reader.
         read_dir.find_map(|res| match res {
@@ -199,7 +672,12 @@ impl<'a> Iterator for SelectedSubPaths<'a> {
                 let sub_path = dir_entry.path();
                 match selection.is_selected(&sub_path) {
                     Ok(true) => Some(Ok(sub_path)),
-                    Ok(false) => None,
+                    Ok(false) => {
+                        if let Some(rejected) = opt_rejected.as_mut() {
+                            rejected.push(sub_path);
+                        }
+                        None
+                    },
                     Err(err) => Some(Err(err)),
                 }
             }
@@ -212,9 +690,12 @@ impl<'a> Iterator for SelectedSubPaths<'a> {
 mod tests {
     use super::*;
 
+    use std::convert::TryInto;
+
     use maplit::hashset;
 
     use crate::config::Sorter;
+    use crate::config::sorter::{SortBy, SortOrder};
     use crate::test_util::TestUtil;
 
     const SAMPLE_FILE_NAMES: &[&str] = &[
@@ -274,6 +755,35 @@ mod tests {
         assert_eq!(selection.is_file_pattern_match(&"path/to/music.ogg"), false);
         assert_eq!(selection.is_file_pattern_match(&"path/to/music.aac"), false);
 
+        // A single pattern with brace alternates.
+        let text = r#"
+            include_files = "*.{flac,wav}"
+        "#;
+        let selection_repr: SelectionRepr = toml::from_str(text).unwrap();
+        let selection: Selection = selection_repr.try_into().unwrap();
+
+        assert!(selection.is_file_pattern_match(&"path/to/music.flac"));
+        assert!(selection.is_file_pattern_match(&"path/to/music.wav"));
+        assert!(!selection.is_file_pattern_match(&"path/to/music.mp3"));
+
+        // Unbalanced braces are an error.
+        let text = r#"
+            include_files = "*.{flac,wav"
+        "#;
+        assert!(toml::from_str::<SelectionRepr>(text).is_err());
+
+        // Glob includes with regex excludes.
+        let text = r#"
+            include_files = "*.flac"
+            exclude_files = { regex = '^\d{4}\.' }
+        "#;
+        let selection_repr: SelectionRepr = toml::from_str(text).unwrap();
+        let selection: Selection = selection_repr.try_into().unwrap();
+
+        assert!(selection.is_file_pattern_match(&"path/to/21.flac"));
+        assert!(!selection.is_file_pattern_match(&"path/to/2021.flac"));
+        assert!(!selection.is_file_pattern_match(&"path/to/2021.mp3"));
+
         // Using a default value for missing include patterns.
         let text = r#"
             exclude_files = ["*.mp3", "*.ogg"]
@@ -360,6 +870,192 @@ mod tests {
         assert_eq!(selection.is_file_pattern_match(&"path/to/music.ogg"), false);
     }
 
+    #[test]
+    fn case_insensitive() {
+        let selection = Selection::new(
+            Matcher::build(&["*.flac"]).unwrap(),
+            Matcher::empty(),
+            Matcher::any(),
+            Matcher::empty(),
+        );
+        assert!(selection.is_file_pattern_match(&"path/to/music.flac"));
+        assert!(!selection.is_file_pattern_match(&"path/to/music.FLAC"));
+
+        let selection = Selection::from_patterns_ci(&["*.flac"], &["ITEM*"], &["*"], &[] as &[&str]).unwrap();
+        assert!(selection.is_file_pattern_match(&"path/to/music.flac"));
+        assert!(selection.is_file_pattern_match(&"path/to/music.FLAC"));
+        assert!(!selection.is_file_pattern_match(&"path/to/item.flac"));
+        assert!(!selection.is_file_pattern_match(&"path/to/Item.FLAC"));
+
+        // Only the lexical content of the path is used, the file does not need to exist.
+        assert!(selection.is_file_pattern_match(&"does/not/exist/MUSIC.Flac"));
+
+        // A case-sensitive include mixed with a case-insensitive exclude.
+        let selection = Selection::new(
+            Matcher::build(&["*.flac"]).unwrap(),
+            Matcher::build_case_insensitive(&["self*"]).unwrap(),
+            Matcher::any(),
+            Matcher::empty(),
+        );
+        assert!(selection.is_file_pattern_match(&"path/to/music.flac"));
+        assert!(!selection.is_file_pattern_match(&"path/to/music.FLAC"));
+        assert!(!selection.is_file_pattern_match(&"path/to/self.flac"));
+        assert!(!selection.is_file_pattern_match(&"path/to/SELF.flac"));
+
+        // Deserializing with the flag applies it to all patterns.
+        let text = r#"
+            include_files = "*.flac"
+            exclude_files = "self*"
+            case_insensitive = true
+        "#;
+        let selection_repr: SelectionRepr = toml::from_str(text).unwrap();
+        let selection: Selection = selection_repr.try_into().unwrap();
+
+        assert!(selection.is_file_pattern_match(&"path/to/music.FLAC"));
+        assert!(!selection.is_file_pattern_match(&"path/to/Self.FLAC"));
+    }
+
+    #[test]
+    fn explain() {
+        let selection = Selection::from_patterns(
+            &["*.flac", "*.{wav,mp3}"],
+            &["self*", "*.mp3"],
+            &["*"],
+            &["_*"],
+        ).unwrap();
+
+        let explanation = selection.explain(&"path/to/music.flac", FileOrDir::File);
+        assert_eq!(explanation, SelectionExplanation {
+            file_or_dir: FileOrDir::File,
+            include: Some(String::from("*.flac")),
+            exclude: None,
+        });
+        assert!(explanation.is_selected());
+
+        let explanation = selection.explain(&"path/to/music.mp3", FileOrDir::File);
+        assert_eq!(explanation.include.as_deref(), Some("*.{wav,mp3}"));
+        assert_eq!(explanation.exclude.as_deref(), Some("*.mp3"));
+        assert!(!explanation.is_selected());
+
+        let explanation = selection.explain(&"path/to/self.ogg", FileOrDir::File);
+        assert_eq!(explanation.include, None);
+        assert_eq!(explanation.exclude.as_deref(), Some("self*"));
+        assert!(!explanation.is_selected());
+
+        // Directories are checked against the directory patterns.
+        let explanation = selection.explain(&"path/to/music.flac", FileOrDir::Dir);
+        assert_eq!(explanation.include.as_deref(), Some("*"));
+        assert_eq!(explanation.exclude, None);
+        assert!(explanation.is_selected());
+
+        let explanation = selection.explain(&"path/to/_hidden", FileOrDir::Dir);
+        assert_eq!(explanation.include.as_deref(), Some("*"));
+        assert_eq!(explanation.exclude.as_deref(), Some("_*"));
+        assert!(!explanation.is_selected());
+
+        // Explanations agree with pattern matching.
+        for name in SAMPLE_FILE_NAMES {
+            assert_eq!(selection.explain(name, FileOrDir::File).is_selected(), selection.is_file_pattern_match(name));
+            assert_eq!(selection.explain(name, FileOrDir::Dir).is_selected(), selection.is_dir_pattern_match(name));
+        }
+    }
+
+    #[test]
+    fn lint() {
+        // The default selection has nothing to report.
+        assert_eq!(Vec::<SelectionLint>::new(), Selection::default().lint());
+
+        // Overlapping patterns.
+        let selection = Selection::from_patterns(
+            &["*.flac", "*.mp3"],
+            &["*.flac"],
+            &["*"],
+            &["*"],
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                SelectionLint::OverlappingPattern(FileOrDir::File, String::from("*.flac")),
+                SelectionLint::OverlappingPattern(FileOrDir::Dir, String::from("*")),
+            ],
+            selection.lint(),
+        );
+
+        // Empty includes.
+        let selection = Selection::new(
+            Matcher::empty(),
+            Matcher::empty(),
+            Matcher::empty(),
+            Matcher::empty(),
+        );
+        assert_eq!(
+            vec![
+                SelectionLint::EmptyInclude(FileOrDir::File),
+                SelectionLint::EmptyInclude(FileOrDir::Dir),
+            ],
+            selection.lint(),
+        );
+
+        // Literal excludes that the includes never match.
+        let selection = Selection::from_patterns(
+            &["*.flac"],
+            &["cover.jpg", "bad.flac", "*.jpg"],
+            &["*"],
+            &["skip"],
+        )
+        .unwrap();
+        assert_eq!(
+            vec![SelectionLint::UselessExclude(FileOrDir::File, String::from("cover.jpg"))],
+            selection.lint(),
+        );
+    }
+
+    #[test]
+    fn filter_entries() {
+        // Obtain a real file type for each kind without needing a temp dir.
+        let file_type = std::fs::metadata(std::env::current_exe().unwrap()).unwrap().file_type();
+        let dir_type = std::fs::metadata(std::env::current_dir().unwrap()).unwrap().file_type();
+
+        let entries = vec![
+            (PathBuf::from("path/to/music.flac"), file_type),
+            (PathBuf::from("path/to/music.mp3"), file_type),
+            (PathBuf::from("path/to/item.yml"), file_type),
+            (PathBuf::from("path/to/ALBUM_01"), dir_type),
+            (PathBuf::from("path/to/ALBUM_02.flac"), dir_type),
+            (PathBuf::from("path/to/_hidden"), dir_type),
+        ];
+
+        let selection = Selection::new(
+            Matcher::build(&["*.flac"]).unwrap(),
+            Matcher::empty(),
+            Matcher::any(),
+            Matcher::build(&["_*"]).unwrap(),
+        );
+        let expected = vec![
+            PathBuf::from("path/to/music.flac"),
+            PathBuf::from("path/to/ALBUM_01"),
+            PathBuf::from("path/to/ALBUM_02.flac"),
+        ];
+        let produced = selection.filter_entries(entries.clone());
+        assert_eq!(expected, produced);
+
+        let selection = Selection::new(
+            Matcher::any(),
+            Matcher::build(&["*.yml"]).unwrap(),
+            Matcher::empty(),
+            Matcher::empty(),
+        );
+        let expected = vec![
+            PathBuf::from("path/to/music.flac"),
+            PathBuf::from("path/to/music.mp3"),
+        ];
+        let produced = selection.filter_entries(entries.clone());
+        assert_eq!(expected, produced);
+
+        let produced = selection.filter_entries(vec![]);
+        assert!(produced.is_empty());
+    }
+
     #[test]
     fn select_in_dir() {
         let temp_dir = TestUtil::create_simple_dir("select_in_dir", SAMPLE_FILE_NAMES);
@@ -432,6 +1128,130 @@ mod tests {
         assert_eq!(expected, produced);
     }
 
+    #[test]
+    fn select_in_dir_partitioned() {
+        let temp_dir = TestUtil::create_plain_fanout_test_dir("select_in_dir_partitioned", 3, 2);
+        let path = temp_dir.path().join("1");
+
+        // Add some files alongside the fanout directories.
+        std::fs::write(path.join("a.flac"), "").unwrap();
+        std::fs::write(path.join("b.mp3"), "").unwrap();
+
+        let selection = Selection::from_patterns(&["*.flac"], &[] as &[&str], &["*"], &["*_2"]).unwrap();
+        let (files, dirs, errs) = selection.select_in_dir_partitioned(&path).unwrap();
+
+        assert_eq!(hashset![path.join("a.flac")], files.into_iter().collect());
+        assert_eq!(hashset![path.join("1_0"), path.join("1_1")], dirs.into_iter().collect());
+        assert!(errs.is_empty());
+
+        // Sub-paths that cannot be accessed are collected as errors.
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(path.join("missing"), path.join("broken")).unwrap();
+
+            let (files, dirs, errs) = selection.select_in_dir_partitioned(&path).unwrap();
+            assert_eq!(files.len(), 1);
+            assert_eq!(dirs.len(), 2);
+            assert_eq!(errs.len(), 1);
+        }
+
+        // Errors accessing the directory itself are returned at the top level.
+        assert!(selection.select_in_dir_partitioned(&path.join("missing")).is_err());
+    }
+
+    #[test]
+    fn file_size_bounds() {
+        let temp_dir = TestUtil::create_simple_dir("file_size_bounds", &[] as &[&str]);
+        let path = temp_dir.path();
+
+        for (name, size) in &[("a", 0), ("b", 1023), ("c", 1024), ("d", 2048), ("e", 2049)] {
+            std::fs::write(path.join(name), vec![0u8; *size]).unwrap();
+        }
+        std::fs::create_dir(path.join("dir")).unwrap();
+
+        let text = r#"
+            min_file_size = "1KiB"
+            max_file_size = 2048
+        "#;
+        let selection_repr: SelectionRepr = toml::from_str(text).unwrap();
+        let selection: Selection = selection_repr.try_into().unwrap();
+
+        // The bounds are inclusive, and never apply to directories.
+        assert!(!selection.is_selected(&path.join("a")).unwrap());
+        assert!(!selection.is_selected(&path.join("b")).unwrap());
+        assert!(selection.is_selected(&path.join("c")).unwrap());
+        assert!(selection.is_selected(&path.join("d")).unwrap());
+        assert!(!selection.is_selected(&path.join("e")).unwrap());
+        assert!(selection.is_selected(&path.join("dir")).unwrap());
+
+        // Pattern matching stays lexical, and ignores the bounds.
+        assert!(selection.is_file_pattern_match(&path.join("a")));
+
+        let expected = hashset![path.join("c"), path.join("d"), path.join("dir")];
+        let produced = selection.select_in_dir(path).unwrap().map(Result::unwrap).collect();
+        assert_eq!(expected, produced);
+
+        // Only one bound may be given.
+        let selection = Selection::default().with_file_size_bounds(None, Some(1023));
+        assert!(selection.is_selected(&path.join("a")).unwrap());
+        assert!(selection.is_selected(&path.join("b")).unwrap());
+        assert!(!selection.is_selected(&path.join("c")).unwrap());
+
+        // Sizes that cannot be parsed are a deserialization error.
+        assert!(toml::from_str::<SelectionRepr>(r#"min_file_size = "1 parsec""#).is_err());
+    }
+
+    #[test]
+    fn select_recursive() {
+        let temp_dir = TestUtil::create_plain_fanout_test_dir("select_recursive", 3, 2);
+        let root = temp_dir.path();
+
+        // Add files at shallower depths.
+        std::fs::write(root.join("root_file"), "").unwrap();
+        std::fs::write(root.join("0").join("0_file"), "").unwrap();
+
+        let sorter = Sorter::default();
+        let selection = Selection::default();
+
+        let collect = |selection: &Selection, max_depth| {
+            selection
+                .select_recursive(root, max_depth, &sorter)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        // Only direct children of the root.
+        assert_eq!(collect(&selection, Some(0)), vec![root.join("root_file")]);
+
+        // Files in each directory come before those of its sub-directories.
+        assert_eq!(collect(&selection, Some(1)), vec![root.join("root_file"), root.join("0").join("0_file")]);
+
+        let produced = collect(&selection, Some(2));
+        assert_eq!(produced.len(), 2 + 27);
+        assert_eq!(produced[..5], [
+            root.join("root_file"),
+            root.join("0").join("0_file"),
+            root.join("0").join("0_0").join("0_0_0"),
+            root.join("0").join("0_0").join("0_0_1"),
+            root.join("0").join("0_0").join("0_0_2"),
+        ]);
+        assert_eq!(produced.last(), Some(&root.join("2").join("2_2").join("2_2_2")));
+
+        assert_eq!(collect(&selection, None), produced);
+
+        // Excluded directories are not descended into.
+        let selection = Selection::from_patterns(&["*"], &[] as &[&str], &["*"], &["*_1", "2"]).unwrap();
+        let produced = collect(&selection, None);
+        assert_eq!(produced.len(), 2 + 12);
+        assert!(produced.iter().all(|p| !p.starts_with(root.join("2"))));
+        assert!(produced.iter().all(|p| !p.to_string_lossy().contains("_1/")));
+
+        // A missing root yields a single error.
+        let mut iter = selection.select_recursive(&root.join("missing"), None, &sorter);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
     #[test]
     fn select_in_dir_sorted() {
         let temp_dir = TestUtil::create_simple_dir("select_in_dir_sorted", SAMPLE_FILE_NAMES);
@@ -508,4 +1328,72 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(expected, produced);
     }
+
+    #[test]
+    fn select_in_dir_sorted_with_rejected() {
+        let temp_dir = TestUtil::create_simple_dir("select_in_dir_sorted_with_rejected", SAMPLE_FILE_NAMES);
+        let path = temp_dir.path();
+        let selection = Selection::new(
+            Matcher::build(&["*.flac"]).unwrap(),
+            Matcher::empty(),
+            Matcher::any(),
+            Matcher::empty(),
+        );
+        let sorter = Sorter::default();
+
+        let (res_paths, mut rejected) = selection.select_in_dir_sorted_with_rejected(path, &sorter).unwrap();
+
+        let expected = selection.select_in_dir_sorted(path, &sorter).unwrap()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let produced = res_paths.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(expected, produced);
+
+        // Every other path was rejected.
+        let mut expected_rejected = SAMPLE_FILE_NAMES.iter()
+            .map(|n| path.join(n))
+            .filter(|p| !produced.contains(p))
+            .collect::<Vec<_>>();
+        expected_rejected.sort();
+        rejected.sort();
+        assert!(!rejected.is_empty());
+        assert_eq!(expected_rejected, rejected);
+    }
+
+    #[test]
+    fn select_in_dir_sorted_limited() {
+        let temp_dir = TestUtil::create_simple_dir("select_in_dir_sorted_limited", SAMPLE_FILE_NAMES);
+        let path = temp_dir.path();
+        let selection = Selection::default();
+
+        // The sample files are all empty, so sorting by size only has ties,
+        // which must keep the same order as a full sort.
+        let sorters = vec![
+            Sorter::default(),
+            Sorter::new(SortBy::Name, SortOrder::Descending),
+            Sorter::new(SortBy::FileSize, SortOrder::Ascending),
+            Sorter::new(SortBy::FileSize, SortOrder::Descending),
+        ];
+
+        for sorter in sorters {
+            let full = selection
+                .select_in_dir_sorted(path, &sorter)
+                .unwrap()
+                .into_iter()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>();
+
+            for limit in 0..=(SAMPLE_FILE_NAMES.len() + 1) {
+                let expected = full.iter().take(limit).cloned().collect::<Vec<_>>();
+                let produced = selection
+                    .select_in_dir_sorted_limited(path, &sorter, limit)
+                    .unwrap()
+                    .into_iter()
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>();
+                assert_eq!(expected, produced);
+            }
+        }
+    }
 }
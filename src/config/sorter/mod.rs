@@ -3,8 +3,14 @@
 pub mod sort_by;
 
 use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
 use std::path::Path;
+use std::sync::Arc;
 
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use serde::Deserialize;
 
 pub use self::sort_by::SortBy;
@@ -23,16 +29,107 @@ impl Default for SortOrder {
     }
 }
 
+/// Represents where item paths that are missing a sort key should be placed,
+/// regardless of the direction of ordering.
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NullPosition {
+    First,
+    #[default]
+    Last,
+}
+
+impl NullPosition {
+    /// The ordering of a path missing a sort key relative to one that has one.
+    pub(crate) fn missing_ordering(self) -> Ordering {
+        match self {
+            Self::First => Ordering::Less,
+            Self::Last => Ordering::Greater,
+        }
+    }
+}
+
+type CmpFn = dyn Fn(&Path, &Path) -> Ordering + Send + Sync;
+
+/// A custom comparison function for item paths, set programmatically.
+/// Two comparators are considered equal only if they are the same function
+/// instance.
+#[derive(Clone, Default)]
+struct Comparator(Option<Arc<CmpFn>>);
+
+impl Debug for Comparator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.0 {
+            Some(..) => write!(f, "Comparator(<custom>)"),
+            None => write!(f, "Comparator(None)"),
+        }
+    }
+}
+
+impl PartialEq for Comparator {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Comparator {}
+
+impl Hash for Comparator {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ref().map(|a| Arc::as_ptr(a) as *const () as usize).hash(state)
+    }
+}
+
 /// A struct that contains all of the information needed to sort item file paths
 /// in a desired order.
-#[derive(Debug, Copy, Clone, Deserialize, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash, Default)]
 #[serde(default, deny_unknown_fields)]
 pub struct Sorter {
     pub sort_by: SortBy,
     pub sort_order: SortOrder,
+    pub null_position: NullPosition,
+    pub seed: Option<u64>,
+    #[serde(skip)]
+    comparator: Comparator,
 }
 
 impl Sorter {
+    pub fn new(sort_by: SortBy, sort_order: SortOrder) -> Self {
+        Self {
+            sort_by,
+            sort_order,
+            null_position: NullPosition::default(),
+            seed: None,
+            comparator: Comparator::default(),
+        }
+    }
+
+    /// Sets where item paths that are missing a sort key should be placed.
+    pub fn with_null_position(mut self, null_position: NullPosition) -> Self {
+        self.null_position = null_position;
+        self
+    }
+
+    /// Sets the seed used to shuffle item paths when sorting by `Random`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Overrides the comparison of item paths with a custom function, which
+    /// takes precedence over `sort_by`. The sort order is still applied to the
+    /// results of the custom function, and errors are still placed first when
+    /// sorting path results. This is only available programmatically; sorters
+    /// deserialized from config always use `sort_by`.
+    pub fn with_comparator(mut self, cmp: Box<CmpFn>) -> Self {
+        self.comparator = Comparator(Some(Arc::from(cmp)));
+        self
+    }
+
     fn align(&self, asc_ord: Ordering) -> Ordering {
         match self.sort_order {
             SortOrder::Ascending => asc_ord,
@@ -40,27 +137,66 @@ impl Sorter {
         }
     }
 
+    /// Returns true if this sorter shuffles item paths instead of ordering them.
+    pub(crate) fn is_random(&self) -> bool {
+        self.sort_by == SortBy::Random && self.comparator.0.is_none()
+    }
+
+    /// Shuffles items, using the seed if one was provided.
+    /// The same seed and number of items always results in the same shuffle.
+    fn shuffle<T>(&self, items: &mut [T]) {
+        match self.seed {
+            Some(seed) => items.shuffle(&mut StdRng::seed_from_u64(seed)),
+            None => items.shuffle(&mut rand::thread_rng()),
+        }
+    }
+
     /// Compares two absolute item paths using this sorting criteria.
+    /// When sorting by `Random`, this always returns `Ordering::Equal`, since
+    /// a shuffle is not a total order; use `sort_paths` instead.
     pub fn cmp_paths<P>(&self, abs_path_a: &P, abs_path_b: &P) -> Ordering
     where
         P: AsRef<Path>,
     {
-        self.align(self.sort_by.cmp_paths(abs_path_a, abs_path_b))
+        match &self.comparator.0 {
+            Some(cmp) => self.align(cmp(abs_path_a.as_ref(), abs_path_b.as_ref())),
+            None => self.sort_by.cmp_paths_aligned(
+                abs_path_a,
+                abs_path_b,
+                |asc_ord| self.align(asc_ord),
+                self.null_position,
+            ),
+        }
     }
 
+    /// Sorts absolute item paths using this sorting criteria.
+    /// When sorting by `Random`, the paths are shuffled instead. They are put
+    /// in name order beforehand, so that a seeded shuffle is reproducible
+    /// regardless of the original order of the paths.
     pub fn sort_paths<P>(&self, paths: &mut [P])
     where
         P: AsRef<Path>,
     {
-        paths.sort_by(|a, b| self.cmp_paths(a, b));
+        if self.is_random() {
+            paths.sort_by(|a, b| SortBy::Name.cmp_paths(a, b));
+            self.shuffle(paths);
+        } else {
+            paths.sort_by(|a, b| self.cmp_paths(a, b));
+        }
     }
 
+    /// Sorts absolute item path results using this sorting criteria, placing
+    /// all errors at the front. When sorting by `Random`, the successful paths
+    /// are shuffled, as with `sort_paths`.
     pub fn sort_path_results<P, E>(&self, res_paths: &mut [Result<P, E>])
     where
         P: AsRef<Path>,
     {
+        let is_random = self.is_random();
+
         res_paths.sort_by(|res_a, res_b| {
             match (res_a, res_b) {
+                (Ok(a), Ok(b)) if is_random => SortBy::Name.cmp_paths(a, b),
                 (Ok(a), Ok(b)) => self.cmp_paths(a, b),
 
                 // These should ensure that errors always get sorted to the front.
@@ -68,7 +204,12 @@ impl Sorter {
                 (Ok(_), Err(_)) => Ordering::Greater,
                 (Err(_), Err(_)) => Ordering::Equal,
             }
-        })
+        });
+
+        if is_random {
+            let num_errs = res_paths.iter().take_while(|res| res.is_err()).count();
+            self.shuffle(&mut res_paths[num_errs..]);
+        }
     }
 }
 
@@ -78,6 +219,7 @@ mod tests {
 
     use rand::seq::SliceRandom;
 
+    use crate::config::Selection;
     use crate::test_util::TestUtil;
 
     #[test]
@@ -100,10 +242,7 @@ mod tests {
             temp_dir_path.join("file_d"),
             temp_dir_path.join("file_e"),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::Name,
-            sort_order: SortOrder::Ascending,
-        };
+        let sorter = Sorter::new(SortBy::Name, SortOrder::Ascending);
         let mut produced = input.clone();
         sorter.sort_paths(&mut produced);
         assert_eq!(produced, expected);
@@ -116,10 +255,7 @@ mod tests {
             temp_dir_path.join("file_b"),
             temp_dir_path.join("file_a"),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::Name,
-            sort_order: SortOrder::Descending,
-        };
+        let sorter = Sorter::new(SortBy::Name, SortOrder::Descending);
         let mut produced = input.clone();
         sorter.sort_paths(&mut produced);
         assert_eq!(produced, expected);
@@ -132,10 +268,7 @@ mod tests {
             temp_dir_path.join("file_c"),
             temp_dir_path.join("file_d"),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::ModTime,
-            sort_order: SortOrder::Ascending,
-        };
+        let sorter = Sorter::new(SortBy::ModTime, SortOrder::Ascending);
         let mut produced = input.clone();
         sorter.sort_paths(&mut produced);
         assert_eq!(produced, expected);
@@ -148,10 +281,7 @@ mod tests {
             temp_dir_path.join("file_e"),
             temp_dir_path.join("file_b"),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::ModTime,
-            sort_order: SortOrder::Descending,
-        };
+        let sorter = Sorter::new(SortBy::ModTime, SortOrder::Descending);
         let mut produced = input.clone();
         sorter.sort_paths(&mut produced);
         assert_eq!(produced, expected);
@@ -198,10 +328,7 @@ mod tests {
             Ok(temp_dir_path.join("file_d")),
             Ok(temp_dir_path.join("file_e")),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::Name,
-            sort_order: SortOrder::Ascending,
-        };
+        let sorter = Sorter::new(SortBy::Name, SortOrder::Ascending);
         let mut produced = input.clone();
         sorter.sort_path_results(&mut produced);
         assert_eq!(produced, expected);
@@ -217,10 +344,7 @@ mod tests {
             Ok(temp_dir_path.join("file_b")),
             Ok(temp_dir_path.join("file_a")),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::Name,
-            sort_order: SortOrder::Descending,
-        };
+        let sorter = Sorter::new(SortBy::Name, SortOrder::Descending);
         let mut produced = input.clone();
         sorter.sort_path_results(&mut produced);
         assert_eq!(produced, expected);
@@ -236,10 +360,7 @@ mod tests {
             Ok(temp_dir_path.join("file_c")),
             Ok(temp_dir_path.join("file_d")),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::ModTime,
-            sort_order: SortOrder::Ascending,
-        };
+        let sorter = Sorter::new(SortBy::ModTime, SortOrder::Ascending);
         let mut produced = input.clone();
         sorter.sort_path_results(&mut produced);
         assert_eq!(produced, expected);
@@ -255,12 +376,273 @@ mod tests {
             Ok(temp_dir_path.join("file_e")),
             Ok(temp_dir_path.join("file_b")),
         ];
-        let sorter = Sorter {
-            sort_by: SortBy::ModTime,
-            sort_order: SortOrder::Descending,
-        };
+        let sorter = Sorter::new(SortBy::ModTime, SortOrder::Descending);
+        let mut produced = input.clone();
+        sorter.sort_path_results(&mut produced);
+        assert_eq!(produced, expected);
+    }
+
+    #[test]
+    fn null_position() {
+        let file_names = &["file_b", "file_a", "file_c"];
+        let temp_dir = TestUtil::create_simple_dir("null_position", file_names);
+        let temp_dir_path = temp_dir.path();
+
+        // Set explicit mod times, so that the order is not timing-dependent.
+        let base_time = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        for (i, name) in file_names.iter().enumerate() {
+            let mtime = base_time + std::time::Duration::from_secs(i as u64);
+            std::fs::File::options()
+                .write(true)
+                .open(temp_dir_path.join(name))
+                .unwrap()
+                .set_modified(mtime)
+                .unwrap();
+        }
+
+        // A nonexistent file has no mod time, and a root path has no file name.
+        let no_mtime = temp_dir_path.join("file_missing");
+        let no_name = Path::new("/").to_path_buf();
+
+        let input = vec![
+            temp_dir_path.join("file_b"),
+            no_mtime.clone(),
+            temp_dir_path.join("file_a"),
+            temp_dir_path.join("file_c"),
+        ];
+
+        let inputs_and_expected = vec![
+            (
+                (SortOrder::Ascending, NullPosition::Last),
+                vec!["file_b", "file_a", "file_c", "file_missing"],
+            ),
+            (
+                (SortOrder::Descending, NullPosition::Last),
+                vec!["file_c", "file_a", "file_b", "file_missing"],
+            ),
+            (
+                (SortOrder::Ascending, NullPosition::First),
+                vec!["file_missing", "file_b", "file_a", "file_c"],
+            ),
+            (
+                (SortOrder::Descending, NullPosition::First),
+                vec!["file_missing", "file_c", "file_a", "file_b"],
+            ),
+        ];
+
+        for ((sort_order, null_position), expected) in inputs_and_expected {
+            let expected = expected
+                .into_iter()
+                .map(|n| temp_dir_path.join(n))
+                .collect::<Vec<_>>();
+
+            let sorter = Sorter::new(SortBy::ModTime, sort_order).with_null_position(null_position);
+            let mut produced = input.clone();
+            sorter.sort_paths(&mut produced);
+            assert_eq!(produced, expected);
+        }
+
+        // Sorting by name, with a path that has no file name.
+        let input = vec![
+            temp_dir_path.join("file_b"),
+            no_name.clone(),
+            temp_dir_path.join("file_a"),
+        ];
+
+        let sorter = Sorter::new(SortBy::Name, SortOrder::Descending);
         let mut produced = input.clone();
+        sorter.sort_paths(&mut produced);
+        assert_eq!(produced, vec![temp_dir_path.join("file_b"), temp_dir_path.join("file_a"), no_name.clone()]);
+
+        let sorter = sorter.with_null_position(NullPosition::First);
+        let mut produced = input.clone();
+        sorter.sort_paths(&mut produced);
+        assert_eq!(produced, vec![no_name.clone(), temp_dir_path.join("file_b"), temp_dir_path.join("file_a")]);
+
+        // Deserializing, defaulting to last.
+        let sorter: Sorter = toml::from_str(r#"null_position = "first""#).unwrap();
+        assert_eq!(sorter.null_position, NullPosition::First);
+        let sorter: Sorter = toml::from_str("").unwrap();
+        assert_eq!(sorter.null_position, NullPosition::Last);
+    }
+
+    #[test]
+    fn sort_file_size() {
+        let temp_dir = TestUtil::create_simple_dir("sort_file_size", &[] as &[&str]);
+        let temp_dir_path = temp_dir.path();
+
+        let names_and_sizes = &[("file_a", 30), ("file_b", 10), ("file_c", 20), ("file_d", 0)];
+        for (name, size) in names_and_sizes {
+            std::fs::write(temp_dir_path.join(name), vec![0u8; *size]).unwrap();
+        }
+
+        let mut input = names_and_sizes
+            .iter()
+            .map(|(n, _)| temp_dir_path.join(n))
+            .collect::<Vec<_>>();
+        input.push(temp_dir_path.join("file_missing"));
+        input.shuffle(&mut rand::thread_rng());
+
+        let ascending = vec!["file_d", "file_b", "file_c", "file_a", "file_missing"]
+            .into_iter()
+            .map(|n| temp_dir_path.join(n))
+            .collect::<Vec<_>>();
+        let sorter = Sorter::new(SortBy::FileSize, SortOrder::Ascending);
+        let mut produced = input.clone();
+        sorter.sort_paths(&mut produced);
+        assert_eq!(produced, ascending);
+
+        let descending = vec!["file_a", "file_c", "file_b", "file_d", "file_missing"]
+            .into_iter()
+            .map(|n| temp_dir_path.join(n))
+            .collect::<Vec<_>>();
+        let sorter = Sorter::new(SortBy::FileSize, SortOrder::Descending);
+        let mut produced = input.clone();
+        sorter.sort_paths(&mut produced);
+        assert_eq!(produced, descending);
+
+        // Selecting in a directory uses the same ordering.
+        let produced = Selection::default()
+            .select_in_dir_sorted(temp_dir_path, &sorter)
+            .unwrap()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(produced, &descending[..4]);
+
+        let sorter: Sorter = toml::from_str(r#"sort_by = "file_size""#).unwrap();
+        assert_eq!(sorter.sort_by, SortBy::FileSize);
+    }
+
+    #[test]
+    fn sort_random() {
+        let file_names = (0..20).map(|i| format!("file_{:02}", i)).collect::<Vec<_>>();
+        let temp_dir = TestUtil::create_simple_dir("sort_random", &file_names);
+        let temp_dir_path = temp_dir.path();
+
+        let sorted = file_names
+            .iter()
+            .map(|n| temp_dir_path.join(n))
+            .collect::<Vec<_>>();
+        let mut input = sorted.clone();
+        input.shuffle(&mut rand::thread_rng());
+
+        // Two runs with the same seed produce the same order, even if the
+        // input order differs.
+        let sorter = Sorter::new(SortBy::Random, SortOrder::Ascending).with_seed(42);
+        let mut produced_a = input.clone();
+        sorter.sort_paths(&mut produced_a);
+        let mut produced_b = sorted.clone();
+        sorter.sort_paths(&mut produced_b);
+        assert_eq!(produced_a, produced_b);
+
+        // The result is a permutation of the input.
+        let mut check = produced_a.clone();
+        check.sort();
+        assert_eq!(check, sorted);
+
+        // A different seed produces a different order.
+        let sorter = Sorter::new(SortBy::Random, SortOrder::Ascending).with_seed(43);
+        let mut produced_c = input.clone();
+        sorter.sort_paths(&mut produced_c);
+        assert_ne!(produced_a, produced_c);
+
+        // Comparisons always treat paths as equal.
+        assert_eq!(sorter.cmp_paths(&sorted[0], &sorted[1]), Ordering::Equal);
+
+        // Errors are still sorted to the front, and the paths are shuffled the
+        // same way as when sorting plain paths.
+        let sorter = Sorter::new(SortBy::Random, SortOrder::Ascending).with_seed(42);
+        let mut produced = input
+            .iter()
+            .cloned()
+            .map(Result::Ok)
+            .chain(std::iter::once(Err(())))
+            .collect::<Vec<_>>();
+        produced.shuffle(&mut rand::thread_rng());
         sorter.sort_path_results(&mut produced);
+        assert_eq!(produced[0], Err(()));
+        assert_eq!(
+            produced[1..].iter().cloned().collect::<Result<Vec<_>, _>>().unwrap(),
+            produced_a,
+        );
+
+        // Without a seed, the result is still a permutation of the input.
+        let sorter = Sorter::new(SortBy::Random, SortOrder::Ascending);
+        let mut produced = input.clone();
+        sorter.sort_paths(&mut produced);
+        produced.sort();
+        assert_eq!(produced, sorted);
+
+        // Deserializing.
+        let sorter: Sorter = toml::from_str("sort_by = \"random\"\nseed = 42").unwrap();
+        assert_eq!(sorter, Sorter::new(SortBy::Random, SortOrder::Ascending).with_seed(42));
+        let sorter: Sorter = toml::from_str("sort_by = \"random\"").unwrap();
+        assert_eq!(sorter.seed, None);
+    }
+
+    #[test]
+    fn with_comparator() {
+        let file_names = &["bb", "a", "dddd", "ccc"];
+        let temp_dir = TestUtil::create_simple_dir("with_comparator", file_names);
+        let temp_dir_path = temp_dir.path();
+
+        let input = file_names
+            .iter()
+            .map(|n| temp_dir_path.join(n))
+            .collect::<Vec<_>>();
+
+        // Reverse-length comparator, longest file names first.
+        let reverse_len = |a: &Path, b: &Path| {
+            let len_a = a.file_name().map_or(0, |n| n.len());
+            let len_b = b.file_name().map_or(0, |n| n.len());
+            len_b.cmp(&len_a)
+        };
+
+        // The comparator overrides the sort criteria.
+        let expected = vec![
+            temp_dir_path.join("dddd"),
+            temp_dir_path.join("ccc"),
+            temp_dir_path.join("bb"),
+            temp_dir_path.join("a"),
+        ];
+        let sorter = Sorter::new(SortBy::Name, SortOrder::Ascending)
+            .with_comparator(Box::new(reverse_len));
+        let mut produced = input.clone();
+        sorter.sort_paths(&mut produced);
+        assert_eq!(produced, expected);
+
+        // The sort order is still applied.
+        let expected = vec![
+            temp_dir_path.join("a"),
+            temp_dir_path.join("bb"),
+            temp_dir_path.join("ccc"),
+            temp_dir_path.join("dddd"),
+        ];
+        let sorter = Sorter::new(SortBy::Name, SortOrder::Descending)
+            .with_comparator(Box::new(reverse_len));
+        let mut produced = input.clone();
+        sorter.sort_paths(&mut produced);
         assert_eq!(produced, expected);
+
+        // Errors are still sorted to the front.
+        let mut produced = input
+            .iter()
+            .cloned()
+            .map(Result::Ok)
+            .chain(std::iter::once(Err(())))
+            .collect::<Vec<_>>();
+        sorter.sort_path_results(&mut produced);
+        assert_eq!(produced[0], Err(()));
+        assert_eq!(produced[1], Ok(temp_dir_path.join("a")));
+
+        // Sorters compare equal only when sharing the same comparator.
+        let cloned = sorter.clone();
+        assert_eq!(sorter, cloned);
+        assert_ne!(sorter, Sorter::new(SortBy::Name, SortOrder::Descending));
+        assert_ne!(
+            sorter,
+            Sorter::new(SortBy::Name, SortOrder::Descending).with_comparator(Box::new(reverse_len)),
+        );
     }
 }
@@ -1,43 +1,189 @@
+use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::iter::Peekable;
 use std::path::Path;
+use std::str::Chars;
 
 use serde::Deserialize;
 
+use crate::config::sorter::NullPosition;
 use crate::util::Util;
 
+/// Compares two optional sort keys. Present keys are compared and then aligned,
+/// while missing keys are placed according to the null position.
+fn opt_cmp<T, F>(opt_a: Option<T>, opt_b: Option<T>, align: F, null_position: NullPosition) -> Ordering
+where
+    T: Ord,
+    F: FnOnce(Ordering) -> Ordering,
+{
+    match (opt_a, opt_b) {
+        (Some(a), Some(b)) => align(a.cmp(&b)),
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => null_position.missing_ordering(),
+        (Some(_), None) => null_position.missing_ordering().reverse(),
+    }
+}
+
 fn name_cmp<P: AsRef<Path>>(abs_path_a: &P, abs_path_b: &P) -> Ordering {
     let file_name_a = abs_path_a.as_ref().file_name();
     let file_name_b = abs_path_b.as_ref().file_name();
     file_name_a.cmp(&file_name_b)
 }
 
+/// Consumes and returns a leading run of ASCII digits.
+fn take_digits(chars: &mut Peekable<Chars<'_>>) -> String {
+    let mut digits = String::new();
+
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+
+    digits
+}
+
+/// Compares two strings in natural order, where runs of digits are compared
+/// by numeric value, e.g. `TRACK_2` sorts before `TRACK_10`. If two strings
+/// only differ by leading zeros, the one with fewer leading zeros sorts first,
+/// e.g. `track_7` sorts before `track_007`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut chars_a = a.chars().peekable();
+    let mut chars_b = b.chars().peekable();
+    let mut tiebreak = Ordering::Equal;
+
+    loop {
+        match (chars_a.peek(), chars_b.peek()) {
+            (None, None) => return tiebreak,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let digits_a = take_digits(&mut chars_a);
+                let digits_b = take_digits(&mut chars_b);
+
+                // Compare numerically without parsing, so that any length of
+                // digit run is supported.
+                let trimmed_a = digits_a.trim_start_matches('0');
+                let trimmed_b = digits_b.trim_start_matches('0');
+
+                let ord = trimmed_a.len().cmp(&trimmed_b.len()).then_with(|| trimmed_a.cmp(trimmed_b));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+
+                tiebreak = tiebreak.then(digits_a.len().cmp(&digits_b.len()));
+            }
+            (Some(ca), Some(cb)) => {
+                let ord = ca.cmp(cb);
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+
+                chars_a.next();
+                chars_b.next();
+            }
+        }
+    }
+}
+
+/// A file name that is ordered naturally.
+#[derive(PartialEq, Eq)]
+struct NaturalKey<'a>(Cow<'a, str>);
+
+impl<'a> PartialOrd for NaturalKey<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for NaturalKey<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.0, &other.0)
+    }
+}
+
+fn natural_key(path: &Path) -> Option<NaturalKey<'_>> {
+    path.file_name().map(|f| NaturalKey(f.to_string_lossy()))
+}
+
+fn natural_name_cmp<P: AsRef<Path>>(abs_path_a: &P, abs_path_b: &P) -> Ordering {
+    let key_a = natural_key(abs_path_a.as_ref());
+    let key_b = natural_key(abs_path_b.as_ref());
+    key_a.cmp(&key_b)
+}
+
 fn mtime_cmp<P: AsRef<Path>>(abs_path_a: &P, abs_path_b: &P) -> Ordering {
     let mtime_a = Util::mtime(abs_path_a.as_ref());
     let mtime_b = Util::mtime(abs_path_b.as_ref());
     mtime_a.cmp(&mtime_b)
 }
 
+fn file_size_cmp<P: AsRef<Path>>(abs_path_a: &P, abs_path_b: &P) -> Ordering {
+    let size_a = Util::file_size(abs_path_a.as_ref());
+    let size_b = Util::file_size(abs_path_b.as_ref());
+    size_a.cmp(&size_b)
+}
+
 /// Represents all criteria that can be used for sorting item files.
 #[derive(Debug, Copy, Clone, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum SortBy {
     Name,
+    /// Sorts by file name, comparing runs of digits by numeric value, so that
+    /// `TRACK_2` sorts before `TRACK_10`.
+    Natural,
     ModTime,
+    /// Sorts by size in bytes. This accesses the filesystem; a path whose
+    /// size cannot be read (e.g. a nonexistent file) has no sort key, and is
+    /// placed according to the null position of the `Sorter`.
+    FileSize,
+    /// Shuffles item paths, optionally using a seed set on the `Sorter`.
+    /// This is not a total order, so all paths compare as equal; use
+    /// `Sorter::sort_paths` to actually shuffle.
+    Random,
 }
 
 impl SortBy {
     /// Compares two absolute item paths using this sorting criteria.
+    /// For `Random`, this always returns `Ordering::Equal`.
     pub fn cmp_paths<P>(&self, abs_path_a: &P, abs_path_b: &P) -> Ordering
     where
         P: AsRef<Path>,
     {
         let cmp_func = match self {
             Self::Name => name_cmp,
+            Self::Natural => natural_name_cmp,
             Self::ModTime => mtime_cmp,
+            Self::FileSize => file_size_cmp,
+            Self::Random => |_: &P, _: &P| Ordering::Equal,
         };
 
         cmp_func(abs_path_a, abs_path_b)
     }
+
+    /// Compares two absolute item paths using this sorting criteria, aligning
+    /// the ordering of paths that have a sort key, and placing paths that are
+    /// missing a sort key (e.g. a nonexistent file has no mod time) according
+    /// to a null position, regardless of alignment.
+    pub(crate) fn cmp_paths_aligned<P, F>(
+        &self,
+        abs_path_a: &P,
+        abs_path_b: &P,
+        align: F,
+        null_position: NullPosition,
+    ) -> Ordering
+    where
+        P: AsRef<Path>,
+        F: FnOnce(Ordering) -> Ordering,
+    {
+        let (path_a, path_b) = (abs_path_a.as_ref(), abs_path_b.as_ref());
+
+        match self {
+            Self::Name => opt_cmp(path_a.file_name(), path_b.file_name(), align, null_position),
+            Self::Natural => opt_cmp(natural_key(path_a), natural_key(path_b), align, null_position),
+            Self::ModTime => opt_cmp(Util::mtime(path_a), Util::mtime(path_b), align, null_position),
+            Self::FileSize => opt_cmp(Util::file_size(path_a), Util::file_size(path_b), align, null_position),
+            Self::Random => Ordering::Equal,
+        }
+    }
 }
 
 impl Default for SortBy {
@@ -97,4 +243,44 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn natural_cmp() {
+        assert_eq!(super::natural_cmp("TRACK_2", "TRACK_10"), Ordering::Less);
+        assert_eq!(super::natural_cmp("TRACK_10", "TRACK_2"), Ordering::Greater);
+        assert_eq!(super::natural_cmp("TRACK_2", "TRACK_2"), Ordering::Equal);
+        assert_eq!(super::natural_cmp("a2b10", "a2b9"), Ordering::Greater);
+        assert_eq!(super::natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(super::natural_cmp("a1", "ab"), Ordering::Less);
+        assert_eq!(super::natural_cmp("", ""), Ordering::Equal);
+        assert_eq!(
+            super::natural_cmp("99999999999999999999999999", "100000000000000000000000000"),
+            Ordering::Less,
+        );
+
+        // Leading zeros only break ties.
+        assert_eq!(super::natural_cmp("track_7", "track_007"), Ordering::Less);
+        assert_eq!(super::natural_cmp("track_007", "track_7"), Ordering::Greater);
+        assert_eq!(super::natural_cmp("track_007", "track_8"), Ordering::Less);
+        assert_eq!(super::natural_cmp("track_007a", "track_7b"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural() {
+        let names = (1..=12).map(|i| format!("DISC_{}", i)).collect::<Vec<_>>();
+        let expected = names.iter().map(|n| Path::new("/root").join(n)).collect::<Vec<_>>();
+
+        let mut produced = expected.clone();
+        produced.reverse();
+        produced.sort_by(|a, b| SortBy::Natural.cmp_paths(a, b));
+        assert_eq!(produced, expected);
+
+        // Lexical sorting puts "DISC_10" before "DISC_2".
+        produced.sort_by(|a, b| SortBy::Name.cmp_paths(a, b));
+        assert_ne!(produced, expected);
+
+        // Descending alignment reverses the numeric order.
+        produced.sort_by(|a, b| SortBy::Natural.cmp_paths_aligned(a, b, Ordering::reverse, NullPosition::Last));
+        assert_eq!(produced, expected.iter().rev().cloned().collect::<Vec<_>>());
+    }
 }
@@ -6,24 +6,68 @@ mod util;
 
 #[cfg(test)] mod test_util;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::config::Config;
 use crate::metadata::processor::Processor;
 use crate::types::Block;
 
+pub use crate::metadata::processor::Error;
 pub use crate::util::FileWalker;
 
-pub fn get<P: AsRef<Path>>(path: &P) -> Block {
+/// Gets the metadata block for an item file, using the default config.
+pub fn get<P: AsRef<Path>>(path: &P) -> Result<Block, Error> {
     let config = Config::default();
     get_with_config(path, &config)
 }
 
-pub fn get_with_config<P: AsRef<Path>>(path: &P, config: &Config) -> Block {
+/// Gets the metadata block for an item file, using the selection, sorting,
+/// and sourcing rules of a `Config`.
+/// Malformed metadata and IO errors are returned as `Err`.
+/// Since the library root is not known, index files are not used.
+pub fn get_with_config<P: AsRef<Path>>(path: &P, config: &Config) -> Result<Block, Error> {
     Processor::process_item_file(
         path.as_ref(),
         &config.sourcer,
         &config.selection,
         &config.sorter,
-    ).unwrap()
+    )
+}
+
+/// Lazily yields each selected item path in a directory along with its
+/// metadata block, as provided by the external meta files in that directory.
+pub fn stream_dir<'a>(
+    dir: &'a Path,
+    config: &'a Config,
+) -> impl Iterator<Item = Result<(PathBuf, Block), Error>> + 'a {
+    Processor::stream_dir(dir, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::TestUtil as TU;
+
+    #[test]
+    fn get_with_config() {
+        let temp_dir = TU::create_simple_dir("get_with_config", ["a.flac"]);
+        let path = temp_dir.path();
+        let item_path = path.join("a.flac");
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.yml"]
+            album = ["self.yml"]
+        "#).unwrap();
+
+        std::fs::write(path.join("item.yml"), "- key: value").unwrap();
+        let produced = super::get_with_config(&item_path, &config).unwrap();
+        assert_eq!(produced.get("key"), Some(&TU::s("value")));
+
+        // Malformed metadata is returned as an error instead of panicking.
+        std::fs::write(path.join("item.yml"), "- key: [unclosed").unwrap();
+        let produced = super::get_with_config(&item_path, &config);
+        assert!(matches!(produced, Err(Error::CannotReadMetadata(..))));
+    }
 }
@@ -4,9 +4,11 @@ pub mod item_paths;
 pub mod plexer;
 pub mod processor;
 pub mod schema;
+pub mod summary;
 
 pub use self::schema::{Arity, Schema};
 pub use self::plexer::{Plexer, Error as PlexerError};
 pub use self::processor::Error as ProcessorError;
+pub use self::summary::{summarize, Summary};
 
 pub(crate) use self::schema::SchemaRepr;
@@ -1,6 +1,9 @@
 //! Methods to assign blocks of metadata to their corresponding item file paths.
 
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::hash_map::Entry;
 use std::io::{Error as IoError, Result as IoResult};
 use std::iter::FusedIterator;
 use std::path::Path;
@@ -26,6 +29,18 @@ pub enum Error {
     UnusedTaggedBlock(Block, String),
     #[error("item path does not have a file name: {}", .0.display())]
     NamelessItemPath(PathBuf),
+    #[error(r#"meta block tag "{1}" collides with another tag after normalization"#)]
+    NormalizedTagCollision(Block, String),
+    #[error(r#"item path has the same file name "{1}" as an earlier item path: {}"#, .0.display())]
+    DuplicateItemName(PathBuf, String),
+}
+
+impl Error {
+    /// Returns true if this error is for an item path or meta block that had
+    /// nothing to be paired up with.
+    pub fn is_unpaired(&self) -> bool {
+        matches!(self, Self::UnusedItemPath(..) | Self::UnusedBlock(..) | Self::UnusedTaggedBlock(..))
+    }
 }
 
 type PlexInItem<'a> = IoResult<Cow<'a, Path>>;
@@ -50,17 +65,29 @@ fn pair_up<'a>(
     }
 }
 
-pub struct PlexOne<'a, I>(Option<Block>, I)
+/// Repeatedly calls a plexing step until it produces an item to emit. If
+/// lenient, unpaired item paths and meta blocks are skipped over.
+fn emit<'a, F>(lenient: bool, mut step: F) -> Option<PlexOutItem<'a>>
+where
+    F: FnMut() -> Option<PlexOutItem<'a>>,
+{
+    loop {
+        match step() {
+            Some(Err(ref err)) if lenient && err.is_unpaired() => {},
+            res => return res,
+        }
+    }
+}
+
+pub struct PlexOne<'a, I>(Option<Block>, I, bool)
 where
     I: Iterator<Item = PlexInItem<'a>>;
 
-impl<'a, I> Iterator for PlexOne<'a, I>
+impl<'a, I> PlexOne<'a, I>
 where
     I: Iterator<Item = PlexInItem<'a>>,
 {
-    type Item = PlexOutItem<'a>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    fn step(&mut self) -> Option<PlexOutItem<'a>> {
         let res = self.1.next().transpose();
 
         match res {
@@ -70,16 +97,27 @@ where
     }
 }
 
+impl<'a, I> Iterator for PlexOne<'a, I>
+where
+    I: Iterator<Item = PlexInItem<'a>>,
+{
+    type Item = PlexOutItem<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lenient = self.2;
+        emit(lenient, || self.step())
+    }
+}
+
 pub struct PlexSeq<'a> {
     block_iter: BlockSeqIntoIter,
     err_iter: VecIntoIter<IoError>,
-    path_iter: VecIntoIter<Cow<'a, Path>>
+    path_iter: VecIntoIter<Cow<'a, Path>>,
+    lenient: bool,
 }
 
-impl<'a> Iterator for PlexSeq<'a> {
-    type Item = PlexOutItem<'a>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'a> PlexSeq<'a> {
+    fn step(&mut self) -> Option<PlexOutItem<'a>> {
         if let Some(err) = self.err_iter.next() {
             Some(Err(Error::Io(err)))
         } else {
@@ -88,9 +126,125 @@ impl<'a> Iterator for PlexSeq<'a> {
     }
 }
 
-pub struct PlexMap<'a, I>(BlockMap, I)
+impl<'a> Iterator for PlexSeq<'a> {
+    type Item = PlexOutItem<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lenient = self.lenient;
+        emit(lenient, || self.step())
+    }
+}
+
+/// Plexes a sequence schema against item paths that have already been sorted,
+/// pairing them up lazily as the item paths are read.
+pub struct PlexSeqPresorted<'a, I>
+where
+    I: Iterator<Item = PlexInItem<'a>>,
+{
+    block_iter: BlockSeqIntoIter,
+    path_iter: I,
+    lenient: bool,
+}
+
+impl<'a, I> PlexSeqPresorted<'a, I>
+where
+    I: Iterator<Item = PlexInItem<'a>>,
+{
+    fn step(&mut self) -> Option<PlexOutItem<'a>> {
+        match self.path_iter.next().transpose() {
+            // Errors do not consume a meta block.
+            Err(err) => Some(Err(Error::Io(err))),
+            Ok(opt_path) => pair_up(self.block_iter.next(), opt_path),
+        }
+    }
+}
+
+impl<'a, I> Iterator for PlexSeqPresorted<'a, I>
 where
-    I: Iterator<Item = PlexInItem<'a>>;
+    I: Iterator<Item = PlexInItem<'a>>,
+{
+    type Item = PlexOutItem<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lenient = self.lenient;
+        emit(lenient, || self.step())
+    }
+}
+
+/// A function that normalizes item file names and meta block tags before
+/// they are compared when plexing a mapping.
+pub type Normalizer<'a> = Box<dyn Fn(&str) -> String + 'a>;
+
+pub struct PlexMap<'a, I>
+where
+    I: Iterator<Item = PlexInItem<'a>>,
+{
+    block_map: BlockMap,
+    // Maps normalized tags to their original tags in the block map.
+    // Only present if a normalizer is being used.
+    normalized: Option<(Normalizer<'a>, HashMap<String, String>)>,
+    // The (normalized, if applicable) file names that have been paired with a
+    // meta block so far.
+    consumed: HashSet<String>,
+    err_iter: VecIntoIter<Error>,
+    path_iter: I,
+    lenient: bool,
+}
+
+impl<'a, I> PlexMap<'a, I>
+where
+    I: Iterator<Item = PlexInItem<'a>>,
+{
+    fn new(mut block_map: BlockMap, opt_normalizer: Option<Normalizer<'a>>, path_iter: I) -> Self {
+        let mut errs = Vec::new();
+
+        let normalized = opt_normalizer.map(|normalizer| {
+            let mut lookup = HashMap::new();
+
+            let tags = block_map.keys().cloned().collect::<Vec<_>>();
+
+            for tag in tags {
+                match lookup.entry(normalizer(&tag)) {
+                    // The first tag wins, report and drop any later ones.
+                    Entry::Occupied(..) => {
+                        if let Some(block) = block_map.remove(&tag) {
+                            errs.push(Error::NormalizedTagCollision(block, tag));
+                        }
+                    },
+                    Entry::Vacant(entry) => { entry.insert(tag); },
+                }
+            }
+
+            (normalizer, lookup)
+        });
+
+        Self {
+            block_map,
+            normalized,
+            consumed: HashSet::new(),
+            err_iter: errs.into_iter(),
+            path_iter,
+            lenient: false,
+        }
+    }
+
+    fn lookup_key(&self, name_tag: &str) -> String {
+        match &self.normalized {
+            None => name_tag.to_string(),
+            Some((normalizer, _)) => normalizer(name_tag),
+        }
+    }
+
+    fn remove_block(&mut self, key: &str) -> Option<Block> {
+        match &mut self.normalized {
+            None => self.block_map.remove(key),
+            Some((_, lookup)) => {
+                let tag = lookup.remove(key)?;
+                self.block_map.remove(&tag)
+            },
+        }
+    }
+}
 
 impl<'a, I> Iterator for PlexMap<'a, I>
 where
@@ -99,7 +253,21 @@ where
     type Item = PlexOutItem<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.1.next() {
+        let lenient = self.lenient;
+        emit(lenient, || self.step())
+    }
+}
+
+impl<'a, I> PlexMap<'a, I>
+where
+    I: Iterator<Item = PlexInItem<'a>>,
+{
+    fn step(&mut self) -> Option<PlexOutItem<'a>> {
+        if let Some(err) = self.err_iter.next() {
+            return Some(Err(err));
+        }
+
+        match self.path_iter.next() {
             Some(Err(err)) => Some(Err(Error::Io(err))),
             Some(Ok(path)) => {
                 // Try and obtain a file name from the path, and convert into a
@@ -108,20 +276,32 @@ where
                 match path.file_name().and_then(|os| os.to_str()) {
                     None => Some(Err(Error::NamelessItemPath(path.into()))),
                     Some(name_tag) => {
+                        let key = self.lookup_key(name_tag);
+
                         // See if the tag is in the meta block mapping.
-                        match self.0.remove(name_tag) {
+                        match self.remove_block(&key) {
+                            // An earlier path with the same file name already
+                            // used the matching meta block, report an error.
+                            None if self.consumed.contains(&key) => {
+                                let name_tag = name_tag.to_string();
+                                Some(Err(Error::DuplicateItemName(path.into(), name_tag)))
+                            },
+
                             // No meta block in the mapping had a matching tag, report an error.
                             None => Some(Err(Error::UnusedItemPath(path.into()))),
 
                             // Found a matching meta block, emit a successful plex result.
-                            Some(block) => Some(Ok((path, block))),
+                            Some(block) => {
+                                self.consumed.insert(key);
+                                Some(Ok((path, block)))
+                            },
                         }
                     }
                 }
             }
             None => {
                 // No more file paths, see if there are any more meta blocks.
-                match self.0.pop() {
+                match self.block_map.pop() {
                     // Found an orphaned meta block, report an error.
                     Some((name_tag, block)) => Some(Err(Error::UnusedTaggedBlock(block, name_tag))),
 
@@ -139,6 +319,7 @@ where
 {
     One(PlexOne<'a, I>),
     Seq(PlexSeq<'a>),
+    SeqPresorted(PlexSeqPresorted<'a, I>),
     Map(PlexMap<'a, I>),
 }
 
@@ -152,6 +333,7 @@ where
         match self {
             Self::One(it) => it.next(),
             Self::Seq(it) => it.next(),
+            Self::SeqPresorted(it) => it.next(),
             Self::Map(it) => it.next(),
         }
     }
@@ -165,14 +347,78 @@ where
 {
     /// Creates a new `Plexer`.
     pub fn new<II>(schema: Schema, file_path_iter: II, sorter: &Sorter) -> Self
+    where
+        II: IntoIterator<IntoIter = I, Item = I::Item>,
+    {
+        Self::build(schema, file_path_iter, Some(sorter), None)
+    }
+
+    /// Creates a new `Plexer` that normalizes both item file names and meta
+    /// block tags before comparing them when plexing a mapping schema, e.g. to
+    /// match a file named "My Song.flac" to a tag of "my-song".
+    /// If multiple tags normalize to the same value, the first one is kept,
+    /// and the others are reported as errors before any other results.
+    /// The normalizer has no effect on other schema types.
+    pub fn with_normalizer<II, F>(
+        schema: Schema,
+        file_path_iter: II,
+        sorter: &Sorter,
+        normalizer: F,
+    ) -> Self
+    where
+        II: IntoIterator<IntoIter = I, Item = I::Item>,
+        F: Fn(&str) -> String + 'a,
+    {
+        Self::build(schema, file_path_iter, Some(sorter), Some(Box::new(normalizer)))
+    }
+
+    /// Creates a new `Plexer` from item paths that have already been sorted.
+    /// Unlike `new`, the item paths are not collected and re-sorted when
+    /// plexing a sequence schema, but are instead paired up lazily with meta
+    /// blocks as they are read. It is the caller's responsibility to provide
+    /// them in the same order that the meta blocks are intended to pair with,
+    /// e.g. by using the same `Sorter` via `Selection::select_in_dir_sorted`.
+    /// Since the item paths are not collected, any errors are yielded in the
+    /// position they are read in, instead of before all other results.
+    pub fn new_presorted<II>(schema: Schema, file_path_iter: II) -> Self
+    where
+        II: IntoIterator<IntoIter = I, Item = I::Item>,
+    {
+        Self::build(schema, file_path_iter, None, None)
+    }
+
+    /// Sets whether this `Plexer` is lenient. A lenient `Plexer` silently
+    /// skips over item paths and meta blocks that could not be paired up,
+    /// instead of reporting them as errors. Other errors are still reported.
+    /// By default, a `Plexer` is strict.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        match self {
+            Self::One(it) => it.2 = lenient,
+            Self::Seq(it) => it.lenient = lenient,
+            Self::SeqPresorted(it) => it.lenient = lenient,
+            Self::Map(it) => it.lenient = lenient,
+        }
+    }
+
+    fn build<II>(
+        schema: Schema,
+        file_path_iter: II,
+        opt_sorter: Option<&Sorter>,
+        opt_normalizer: Option<Normalizer<'a>>,
+    ) -> Self
     where
         II: IntoIterator<IntoIter = I, Item = I::Item>,
     {
         let file_path_iter = file_path_iter.into_iter();
 
-        match schema {
-            Schema::One(mb) => Self::One(PlexOne(Some(mb), file_path_iter)),
-            Schema::Seq(mb_seq) => {
+        match (schema, opt_sorter) {
+            (Schema::One(mb), _) => Self::One(PlexOne(Some(mb), file_path_iter, false)),
+            (Schema::Seq(mb_seq), None) => Self::SeqPresorted(PlexSeqPresorted {
+                block_iter: mb_seq.into_iter(),
+                path_iter: file_path_iter,
+                lenient: false,
+            }),
+            (Schema::Seq(mb_seq), Some(sorter)) => {
                 // Need to pre-collect, in order to sort.
                 // Since the entire path iterator needs to be read right now,
                 // just pre-partion the path results into `Ok`/`Err`s.
@@ -192,11 +438,12 @@ where
                     block_iter: mb_seq.into_iter(),
                     err_iter: errs.into_iter(),
                     path_iter: paths.into_iter(),
+                    lenient: false,
                 };
 
                 Self::Seq(plex_seq)
             }
-            Schema::Map(mb_map) => Self::Map(PlexMap(mb_map, file_path_iter)),
+            (Schema::Map(mb_map), _) => Self::Map(PlexMap::new(mb_map, opt_normalizer, file_path_iter)),
         }
     }
 }
@@ -249,6 +496,19 @@ mod tests {
             };
         };
     }
+    macro_rules! assert_duplicate_name {
+        ( $plex:expr, $path:expr, $name:expr ) => {
+            match $plex.next() {
+                Some(Err(Error::DuplicateItemName(ref p, ref n))) => {
+                    assert_eq!(p, &$path);
+                    assert_eq!(n, $name);
+                }
+                Some(Err(e)) => panic!("unexpected error: {}", e),
+                Some(Ok((ref p, ref b))) => panic!("unexpected ok: ({}, {:?})", p.display(), b),
+                None => panic!("unexpected none"),
+            };
+        };
+    }
     macro_rules! assert_extra_block {
         ( $plex:expr, $block:expr ) => {
             match $plex.next() {
@@ -488,5 +748,254 @@ mod tests {
         assert_nameless_path!(plexer, nameless);
         assert_ok!(plexer, path_c, block_c);
         assert_none!(plexer);
+
+        // Duplicate file names.
+        let dup_path_a = Path::new("other").join(name_a);
+        let mut plexer = Plexer::new(
+            schema.clone(),
+            vec![okc(path_a), okc(&dup_path_a), okc(path_b), okc(path_c)],
+            &sorter,
+        );
+        assert_ok!(plexer, path_a, block_a);
+        assert_duplicate_name!(plexer, dup_path_a, name_a);
+        assert_ok!(plexer, path_b, block_b);
+        assert_ok!(plexer, path_c, block_c);
+        assert_none!(plexer);
+    }
+
+    #[test]
+    fn new_presorted() {
+        let block_a = Block(btreemap![str!("key_a") => TU::s("val_a")]);
+        let block_b = Block(btreemap![str!("key_b") => TU::s("val_b")]);
+        let block_c = Block(btreemap![str!("key_c") => TU::s("val_c")]);
+
+        let path_a = Path::new("name_a");
+        let path_b = Path::new("name_b");
+        let path_c = Path::new("name_c");
+        let path_x = Path::new("name_x");
+
+        let sorter = Sorter::default();
+
+        let schema_seq = Schema::Seq(BlockSeq(vec![block_a.clone(), block_b.clone(), block_c.clone()]));
+
+        // Given pre-sorted input, output is identical to the sorting constructor.
+        let expected = Plexer::new(
+            schema_seq.clone(),
+            vec![okc(path_c), okc(path_a), okc(path_b)],
+            &sorter,
+        )
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+        let produced = Plexer::new_presorted(
+            schema_seq.clone(),
+            vec![okc(path_a), okc(path_b), okc(path_c)],
+        )
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+        assert_eq!(expected, produced);
+
+        // Unsorted input is trusted as-is.
+        let mut plexer = Plexer::new_presorted(
+            schema_seq.clone(),
+            vec![okc(path_c), okc(path_a), okc(path_b)],
+        );
+        assert_ok!(plexer, path_c, block_a);
+        assert_ok!(plexer, path_a, block_b);
+        assert_ok!(plexer, path_b, block_c);
+        assert_none!(plexer);
+
+        // Errors are yielded in place, without consuming a meta block.
+        let mut plexer = Plexer::new_presorted(
+            schema_seq.clone(),
+            vec![
+                okc(path_a),
+                Err(IoError::other("sample")),
+                okc(path_b),
+                okc(path_c),
+            ],
+        );
+        assert_ok!(plexer, path_a, block_a);
+        assert_io_error!(plexer);
+        assert_ok!(plexer, path_b, block_b);
+        assert_ok!(plexer, path_c, block_c);
+        assert_none!(plexer);
+
+        // Unused item paths and meta blocks are reported as with `new`.
+        let mut plexer = Plexer::new_presorted(
+            schema_seq.clone(),
+            vec![okc(path_a), okc(path_b), okc(path_c), okc(path_x)],
+        );
+        assert_ok!(plexer, path_a, block_a);
+        assert_ok!(plexer, path_b, block_b);
+        assert_ok!(plexer, path_c, block_c);
+        assert_extra_path!(plexer, path_x);
+        assert_none!(plexer);
+
+        let mut plexer = Plexer::new_presorted(schema_seq.clone(), vec![okc(path_a)]);
+        assert_ok!(plexer, path_a, block_a);
+        assert_extra_block!(plexer, block_b);
+        assert_extra_block!(plexer, block_c);
+        assert_none!(plexer);
+
+        // Item paths are only read as they are needed.
+        let num_read = std::cell::Cell::new(0);
+        let path_iter = vec![okc(path_a), okc(path_b), okc(path_c)]
+            .into_iter()
+            .inspect(|_| num_read.set(num_read.get() + 1))
+            .chain(std::iter::from_fn(|| panic!("item paths were drained early")));
+
+        let mut plexer = Plexer::new_presorted(schema_seq, path_iter);
+        assert_eq!(num_read.get(), 0);
+        assert_ok!(plexer, path_a, block_a);
+        assert_eq!(num_read.get(), 1);
+        assert_ok!(plexer, path_b, block_b);
+        assert_eq!(num_read.get(), 2);
+        assert_ok!(plexer, path_c, block_c);
+        assert_eq!(num_read.get(), 3);
+    }
+
+    #[test]
+    fn set_lenient() {
+        let block_a = Block(btreemap![str!("key_a") => TU::s("val_a")]);
+        let block_b = Block(btreemap![str!("key_b") => TU::s("val_b")]);
+
+        let path_a = Path::new("name_a");
+        let path_b = Path::new("name_b");
+        let path_x = Path::new("name_x");
+        let path_y = Path::new("name_y");
+
+        let sorter = Sorter::default();
+
+        let schema_seq = Schema::Seq(BlockSeq(vec![block_a.clone(), block_b.clone()]));
+
+        // Extra paths are reported when strict, and skipped when lenient.
+        let paths = || vec![okc(path_a), okc(path_x), okc(path_b), okc(path_y)];
+
+        let mut plexer = Plexer::new(schema_seq.clone(), paths(), &sorter);
+        assert_ok!(plexer, path_a, block_a);
+        assert_ok!(plexer, path_b, block_b);
+        assert_extra_path!(plexer, path_x);
+        assert_extra_path!(plexer, path_y);
+        assert_none!(plexer);
+
+        let mut plexer = Plexer::new(schema_seq.clone(), paths(), &sorter);
+        plexer.set_lenient(true);
+        assert_ok!(plexer, path_a, block_a);
+        assert_ok!(plexer, path_b, block_b);
+        assert_none!(plexer);
+
+        // Extra blocks are skipped as well, but other errors are not.
+        let mut plexer = Plexer::new_presorted(
+            schema_seq,
+            vec![okc(path_a), Err(IoError::other("sample"))],
+        );
+        plexer.set_lenient(true);
+        assert_ok!(plexer, path_a, block_a);
+        assert_io_error!(plexer);
+        assert_none!(plexer);
+
+        // Lenient mode can be turned back off.
+        let mut plexer = Plexer::new(Schema::One(block_a.clone()), vec![okc(path_a), okc(path_x)], &sorter);
+        plexer.set_lenient(true);
+        plexer.set_lenient(false);
+        assert_ok!(plexer, path_a, block_a);
+        assert_extra_path!(plexer, path_x);
+        assert_none!(plexer);
+
+        let schema_map = Schema::Map(BlockMap(indexmap![
+            str!("name_a") => block_a.clone(),
+            str!("name_b") => block_b.clone(),
+        ]));
+        let mut plexer = Plexer::new(schema_map, vec![okc(path_x), okc(path_b)], &sorter);
+        plexer.set_lenient(true);
+        assert_ok!(plexer, path_b, block_b);
+        assert_none!(plexer);
+    }
+
+    #[test]
+    fn with_normalizer() {
+        let block_a = Block(btreemap![str!("key_a") => TU::s("val_a")]);
+        let block_b = Block(btreemap![str!("key_b") => TU::s("val_b")]);
+        let block_c = Block(btreemap![str!("key_c") => TU::s("val_c")]);
+
+        let path_a = Path::new("My Song.flac");
+        let path_b = Path::new("Another One.flac");
+        let path_x = Path::new("Missing.flac");
+
+        let sorter = Sorter::default();
+
+        // Lowercases, strips the extension, and replaces spaces with hyphens.
+        let slugify = |s: &str| {
+            let stem = s.rsplitn(2, '.').last().unwrap_or(s);
+            stem.to_lowercase().replace(' ', "-")
+        };
+
+        let schema_map = Schema::Map(BlockMap(indexmap![
+            str!("my-song") => block_a.clone(),
+            str!("another-one") => block_b.clone(),
+        ]));
+
+        let mut plexer = Plexer::with_normalizer(
+            schema_map.clone(),
+            vec![okc(path_a), okc(path_b)],
+            &sorter,
+            slugify,
+        );
+        assert_ok!(plexer, path_a, block_a);
+        assert_ok!(plexer, path_b, block_b);
+        assert_none!(plexer);
+
+        // Without a normalizer, nothing matches.
+        let mut plexer = Plexer::new(schema_map.clone(), vec![okc(path_a)], &sorter);
+        assert_extra_path!(plexer, path_a);
+        assert_extra_tagged_block!(plexer, block_b, str!("another-one"));
+        assert_extra_tagged_block!(plexer, block_a, str!("my-song"));
+        assert_none!(plexer);
+
+        // Unmatched paths and blocks are still reported, with original tags.
+        let mut plexer = Plexer::with_normalizer(
+            schema_map,
+            vec![okc(path_a), okc(path_x)],
+            &sorter,
+            slugify,
+        );
+        assert_ok!(plexer, path_a, block_a);
+        assert_extra_path!(plexer, path_x);
+        assert_extra_tagged_block!(plexer, block_b, str!("another-one"));
+        assert_none!(plexer);
+
+        // Tags that collide after normalization are reported first.
+        let schema_map = Schema::Map(BlockMap(indexmap![
+            str!("my-song") => block_a.clone(),
+            str!("My Song") => block_c.clone(),
+        ]));
+        let mut plexer = Plexer::with_normalizer(
+            schema_map,
+            vec![okc(path_a)],
+            &sorter,
+            slugify,
+        );
+        match plexer.next() {
+            Some(Err(Error::NormalizedTagCollision(b, t))) => {
+                assert_eq!(b, block_c);
+                assert_eq!(t, "My Song");
+            },
+            _ => panic!("expected collision error"),
+        }
+        assert_ok!(plexer, path_a, block_a);
+        assert_none!(plexer);
+
+        // File names that are only the same after normalization are duplicates.
+        let path_d = Path::new("my song.flac");
+        let schema_map = Schema::Map(BlockMap(indexmap![str!("my-song") => block_a.clone()]));
+        let mut plexer = Plexer::with_normalizer(
+            schema_map,
+            vec![okc(path_a), okc(path_d)],
+            &sorter,
+            slugify,
+        );
+        assert_ok!(plexer, path_a, block_a);
+        assert_duplicate_name!(plexer, path_d, "my song.flac");
+        assert_none!(plexer);
     }
 }
@@ -1,15 +1,24 @@
 //! High-level methods for processing meta files and loading item file metadata.
 
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::path::Path;
+use std::io::Error as IoError;
+use std::iter::Peekable;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::SystemTime;
 
 use thiserror::Error;
 
-use crate::config::{Selection, Sorter, FormatError};
+use crate::config::{Config, Format, Selection, Sorter, FormatError};
 use crate::metadata::plexer::{Error as PlexerError, Plexer};
-use crate::sources::{SourceError, Source, Sourcer};
-use crate::types::Block;
+use crate::metadata::schema::{Arity, Schema};
+use crate::sources::{Anchor, SourceError, Source, Sourcer};
+use crate::sources::sourcer::is_format_sibling;
+use crate::types::{Block, Value};
+use crate::util::file_walker::ChildFileWalker;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -23,6 +32,91 @@ pub enum Error {
     PlexerError(#[source] PlexerError),
     #[error("missing metadata")]
     MissingMetadata,
+    #[error("cannot walk item tree: {0}")]
+    CannotWalkTree(#[source] IoError),
+    #[error("index file must contain a mapping of relative item paths to blocks")]
+    InvalidIndex,
+    #[error(r#"index key is not a relative item path: "{0}""#)]
+    InvalidIndexKey(String),
+    #[error("index key does not refer to an existing item: {}", .0.display())]
+    MissingIndexTarget(PathBuf),
+    #[error("item path is not valid Unicode: {}", .0.display())]
+    NonUnicodePath(PathBuf),
+    #[error("item path is not under the root path: {}", .0.display())]
+    NotUnderRoot(PathBuf),
+    #[error("processing was cancelled")]
+    Cancelled,
+}
+
+type ItemResult = Result<(PathBuf, Block), Error>;
+type CacheKey = (PathBuf, Format, Arity);
+
+/// Summary counts of a tree scan, as produced by
+/// `Processor::process_tree_with_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ProcessStats {
+    /// Number of item paths that were selected and visited, including the root.
+    pub selected: usize,
+    /// Number of selected item paths that were processed successfully.
+    pub processed: usize,
+    /// Number of directory entries that were not selected, and thus skipped.
+    /// Meta files and hidden files (whose names start with `.`) are not
+    /// items, and are not counted.
+    pub skipped: usize,
+    /// Number of errors encountered, either while walking or processing.
+    pub errored: usize,
+}
+
+impl std::ops::AddAssign for ProcessStats {
+    fn add_assign(&mut self, other: Self) {
+        self.selected += other.selected;
+        self.processed += other.processed;
+        self.skipped += other.skipped;
+        self.errored += other.errored;
+    }
+}
+
+/// A memo of parsed meta files, for reuse across repeated processing of the
+/// same meta files, such as during a tree walk.
+/// Entries are keyed by meta file path, format, and arity, and are reloaded
+/// whenever the modification time of the meta file changes.
+/// This uses interior mutability, and is meant to be shared within a single
+/// thread.
+#[derive(Debug, Default)]
+pub struct ProcessorCache {
+    entries: RefCell<HashMap<CacheKey, (SystemTime, Schema)>>,
+}
+
+impl ProcessorCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all cached entries.
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    /// Reads the schema of a meta file, using the cached copy if the meta
+    /// file has not been modified since it was last read.
+    pub fn read_schema(&self, meta_path: &Path, source: &Source) -> Result<Schema, Error> {
+        let mtime = std::fs::metadata(meta_path)
+            .and_then(|m| m.modified())
+            .map_err(|e| Error::CannotReadMetadata(FormatError::CannotOpenFile(e)))?;
+
+        let key = (meta_path.to_path_buf(), source.format, source.anchor.into());
+
+        if let Some((cached_mtime, schema)) = self.entries.borrow().get(&key) {
+            if *cached_mtime == mtime {
+                return Ok(schema.clone());
+            }
+        }
+
+        let schema = source.read_schema(meta_path).map_err(Error::CannotReadMetadata)?;
+        self.entries.borrow_mut().insert(key, (mtime, schema.clone()));
+
+        Ok(schema)
+    }
 }
 
 pub struct Processor;
@@ -38,7 +132,28 @@ impl Processor {
         sorter: &'a Sorter,
     ) -> Result<HashMap<Cow<'a, Path>, Block>, Error> {
         let schema = source.read_schema(meta_path).map_err(Error::CannotReadMetadata)?;
+        Self::plex_meta_file(meta_path, schema, source, selection, sorter)
+    }
+
+    /// Like `process_meta_file`, but reads the meta file through a cache.
+    pub fn process_meta_file_cached<'a>(
+        meta_path: &'a Path,
+        source: &'a Source,
+        selection: &'a Selection,
+        sorter: &'a Sorter,
+        cache: &ProcessorCache,
+    ) -> Result<HashMap<Cow<'a, Path>, Block>, Error> {
+        let schema = cache.read_schema(meta_path, source)?;
+        Self::plex_meta_file(meta_path, schema, source, selection, sorter)
+    }
 
+    fn plex_meta_file<'a>(
+        meta_path: &'a Path,
+        schema: Schema,
+        source: &'a Source,
+        selection: &'a Selection,
+        sorter: &'a Sorter,
+    ) -> Result<HashMap<Cow<'a, Path>, Block>, Error> {
         // LEARN: Since `meta_path` is already a ref, no need to add `&`!
         let sel_item_paths = source
             .selected_item_paths(meta_path, selection)
@@ -61,27 +176,90 @@ impl Processor {
     /// targets that may provide data for this item file. Merging is done in a
     /// "combine-last" fashion; if a later target produces the same metadata key
     /// as an earlier target, the later one wins and overwrites the earlier one.
+    /// Meta files of the same source in different formats (e.g. "item.yml"
+    /// and "item.json") that are both used are deep-merged instead, so that
+    /// nested mappings split between them are combined key by key.
+    /// Since the library root is not known, the index is not used; see
+    /// `process_item_file_indexed`.
     pub fn process_item_file(
         item_path: &Path,
         sourcer: &Sourcer,
         selection: &Selection,
         sorter: &Sorter,
+    ) -> Result<Block, Error> {
+        Self::merge_item_file(item_path, sourcer, selection, sorter, None, None)
+    }
+
+    /// Like `process_item_file`, but first merges in the block for the item
+    /// file from a library index, as loaded by `process_index`, so that meta
+    /// files always override index entries.
+    pub fn process_item_file_indexed(
+        item_path: &Path,
+        sourcer: &Sourcer,
+        selection: &Selection,
+        sorter: &Sorter,
+        indexed: &HashMap<PathBuf, Block>,
+    ) -> Result<Block, Error> {
+        Self::merge_item_file(item_path, sourcer, selection, sorter, None, Some(indexed))
+    }
+
+    /// Like `process_item_file`, but reads meta files through a cache, so
+    /// that meta files shared between item files are only parsed once.
+    pub fn process_item_file_cached(
+        item_path: &Path,
+        sourcer: &Sourcer,
+        selection: &Selection,
+        sorter: &Sorter,
+        cache: &ProcessorCache,
+    ) -> Result<Block, Error> {
+        Self::merge_item_file(item_path, sourcer, selection, sorter, Some(cache), None)
+    }
+
+    fn merge_item_file(
+        item_path: &Path,
+        sourcer: &Sourcer,
+        selection: &Selection,
+        sorter: &Sorter,
+        opt_cache: Option<&ProcessorCache>,
+        opt_indexed: Option<&HashMap<PathBuf, Block>>,
     ) -> Result<Block, Error> {
         let mut comp_mb = Block::new();
 
+        if let Some(index_block) = opt_indexed.and_then(|indexed| indexed.get(item_path)) {
+            comp_mb.extend(index_block.clone());
+        }
+
         let meta_paths = sourcer.meta_paths(item_path);
 
+        // Sources whose meta files have already been merged in.
+        let mut merged_sources: Vec<&Source> = Vec::new();
+
         for mps_res in meta_paths {
             let (meta_path, source) = mps_res.map_err(Error::CannotFindMetaPath)?;
 
-            let mut processed_meta_file =
-                Self::process_meta_file(&meta_path, source, selection, sorter)?;
+            let mut processed_meta_file = match opt_cache {
+                Some(cache) => Self::process_meta_file_cached(&meta_path, source, selection, sorter, cache)?,
+                None => Self::process_meta_file(&meta_path, source, selection, sorter)?,
+            };
 
             // The results of processing a meta file will often return extra
             // metadata for item files besides the targeted one. Extract the
             // target item file's metadata, and drop the remaining results.
             if let Some(meta_block) = processed_meta_file.remove(item_path) {
-                comp_mb.extend(meta_block)
+                if merged_sources.iter().any(|merged| is_format_sibling(merged, source)) {
+                    for (key, meta_val) in meta_block {
+                        let merged_val = match comp_mb.remove(&key) {
+                            Some(comp_val) => comp_val.merge(meta_val),
+                            None => meta_val,
+                        };
+
+                        comp_mb.insert(key, merged_val);
+                    }
+                } else {
+                    comp_mb.extend(meta_block)
+                }
+
+                merged_sources.push(source);
             } else {
                 Err(Error::MissingMetadata)?
             }
@@ -89,6 +267,509 @@ impl Processor {
 
         Ok(comp_mb)
     }
+
+    /// Lazily plexes the external meta files of a directory against the
+    /// selected item paths in that directory, yielding each item path along
+    /// with its metadata block.
+    /// Each external meta file that is present is plexed in turn, in source
+    /// order. The multi-format policy is not applied, so an item path may be
+    /// yielded once for each meta file. Errors are yielded as `Err` items, and
+    /// do not stop the iteration.
+    pub fn stream_dir<'a>(dir: &'a Path, config: &'a Config) -> impl Iterator<Item = ItemResult> + 'a {
+        config.sourcer.as_sources()
+            .iter()
+            .filter(|source| matches!(source.anchor, Anchor::External))
+            .map(move |source| (source, dir.join(&source.name)))
+            .filter(|(_, meta_path)| meta_path.is_file())
+            .flat_map(move |(source, meta_path)| -> Box<dyn Iterator<Item = ItemResult> + 'a> {
+                let schema = match source.read_schema(&meta_path) {
+                    Ok(schema) => schema,
+                    Err(err) => return Box::new(std::iter::once(Err(Error::CannotReadMetadata(err)))),
+                };
+
+                let sel_item_paths = match config.selection.select_in_dir(dir) {
+                    Ok(sub_paths) => sub_paths.map(|res| res.map(Cow::Owned)),
+                    Err(err) => {
+                        let err = Error::CannotFindItemPaths(SourceError::IterDir(err));
+                        return Box::new(std::iter::once(Err(err)));
+                    },
+                };
+
+                let meta_plexer = Plexer::new(schema, sel_item_paths, &config.sorter);
+
+                Box::new(meta_plexer.map(|res| {
+                    res.map(|(item_path, block)| (item_path.into_owned(), block))
+                        .map_err(Error::PlexerError)
+                }))
+            })
+    }
+
+    /// Processes an index meta file, which maps item paths (relative to the
+    /// directory containing the index file) to metadata blocks.
+    /// This follows the same rules as plexing a mapping of blocks, except that
+    /// keys are relative paths instead of bare file names. Every key must
+    /// refer to an existing item path, and that item path, along with each of
+    /// its parent directories up to the index directory, must be selected.
+    /// As with plexing, a block for an item path that is not selected is
+    /// reported as an unused block.
+    pub fn process_index_file(
+        index_path: &Path,
+        format: &Format,
+        selection: &Selection,
+    ) -> Result<HashMap<PathBuf, Block>, Error> {
+        let schema = format
+            .read_schema_path(index_path, &Arity::Many)
+            .map_err(Error::CannotReadMetadata)?;
+
+        let block_map = match schema {
+            Schema::Map(block_map) => block_map,
+            Schema::One(..) | Schema::Seq(..) => return Err(Error::InvalidIndex),
+        };
+
+        let index_dir = index_path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut indexed = HashMap::new();
+
+        // Selection checks of paths shared between keys, mostly directories.
+        let mut checked = HashMap::new();
+
+        for (rel_path_str, block) in block_map {
+            let rel_path = Path::new(&rel_path_str);
+
+            // Only allow plain relative paths that stay inside the index dir.
+            let is_plain_relative = rel_path.components().next().is_some()
+                && rel_path.components().all(|c| matches!(c, Component::Normal(..)));
+
+            if !is_plain_relative {
+                return Err(Error::InvalidIndexKey(rel_path_str));
+            }
+
+            let item_path = index_dir.join(rel_path);
+
+            let mut curr_path = index_dir.to_path_buf();
+
+            for component in rel_path.components() {
+                curr_path.push(component);
+
+                let is_selected = match checked.get(&curr_path) {
+                    Some(&is_selected) => is_selected,
+                    None => {
+                        let is_selected = match selection.is_selected(&curr_path) {
+                            Ok(is_selected) => is_selected,
+                            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                                return Err(Error::MissingIndexTarget(item_path));
+                            },
+                            Err(err) => return Err(Error::CannotFindItemPaths(SourceError::IterDir(err))),
+                        };
+
+                        checked.insert(curr_path.clone(), is_selected);
+                        is_selected
+                    },
+                };
+
+                if !is_selected {
+                    return Err(Error::PlexerError(PlexerError::UnusedTaggedBlock(block, rel_path_str)));
+                }
+            }
+
+            indexed.insert(item_path, block);
+        }
+
+        Ok(indexed)
+    }
+
+    /// Processes the index meta file configured for a library root, if any.
+    /// Only the index file directly inside the root is used; index files in
+    /// any other directory, including above the root, are ignored.
+    /// If no index is configured, or the root does not contain an index file,
+    /// an empty mapping is returned.
+    pub fn process_index(root: &Path, config: &Config) -> Result<HashMap<PathBuf, Block>, Error> {
+        match config.sourcer.index() {
+            None => Ok(HashMap::new()),
+            Some(source) => {
+                let index_path = root.join(&source.name);
+
+                if !index_path.is_file() {
+                    return Ok(HashMap::new());
+                }
+
+                Self::process_index_file(&index_path, &source.format, &config.selection)
+            },
+        }
+    }
+
+    /// Processes metadata for every item file in a tree, starting at and
+    /// including a root item path. Items are visited in depth-first order,
+    /// with siblings visited according to the configured sorting order.
+    /// If a cancellation flag is given and gets set, processing stops before
+    /// the next item, and `Error::Cancelled` is returned.
+    pub fn process_tree(
+        root: &Path,
+        config: &Config,
+        cancel: Option<Arc<AtomicBool>>,
+    ) -> Result<Vec<(PathBuf, Block)>, Error> {
+        Self::process_tree_with_progress(root, config, cancel, |_| {})
+    }
+
+    /// Similar to `process_tree`, but also calls a callback with each item
+    /// path once that item has been processed.
+    /// Since the tree is walked lazily, the total number of items is not known
+    /// ahead of time; callers that need a percentage should count the items
+    /// beforehand, e.g. with a `ChildFileWalker`.
+    pub fn process_tree_with_progress<F>(
+        root: &Path,
+        config: &Config,
+        cancel: Option<Arc<AtomicBool>>,
+        mut on_progress: F,
+    ) -> Result<Vec<(PathBuf, Block)>, Error>
+    where
+        F: FnMut(&Path),
+    {
+        let mut results = Vec::new();
+
+        Self::for_each_in_tree(root, config, cancel, |item_path, block| {
+            on_progress(&item_path);
+            results.push((item_path, block));
+            true
+        })?;
+
+        Ok(results)
+    }
+
+    /// Produces the fully-inherited metadata for an item path, by merging the
+    /// metadata of each of its ancestors, starting at and including a root
+    /// path, down to the item itself. Merging follows the same "combine-last"
+    /// rules as `process_item_file`, so nearer items override farther ones.
+    pub fn materialize_item(root: &Path, item_path: &Path, config: &Config) -> Result<Block, Error> {
+        let rel_path = item_path
+            .strip_prefix(root)
+            .map_err(|_| Error::NotUnderRoot(item_path.to_path_buf()))?;
+
+        let indexed = Self::process_index(root, config)?;
+
+        let mut curr_path = root.to_path_buf();
+        let mut materialized = Self::process_item_file_indexed(
+            &curr_path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+            &indexed,
+        )?;
+
+        for component in rel_path.components() {
+            curr_path.push(component);
+
+            materialized.extend(Self::process_item_file_indexed(
+                &curr_path,
+                &config.sourcer,
+                &config.selection,
+                &config.sorter,
+                &indexed,
+            )?);
+        }
+
+        Ok(materialized)
+    }
+
+    /// Similar to `materialize_item`, but produces the fully-inherited
+    /// metadata for every item in a tree, in the same order as `process_tree`.
+    /// Each item is only processed once.
+    pub fn materialize_tree(root: &Path, config: &Config) -> Result<Vec<(PathBuf, Block)>, Error> {
+        let mut results: Vec<(PathBuf, Block)> = Vec::new();
+
+        // Stack of indices into the results of the ancestors of the current item.
+        let mut ancestors: Vec<usize> = Vec::new();
+
+        for (item_path, block) in Self::process_tree(root, config, None)? {
+            // Since items are visited depth-first, pop any items that are not
+            // ancestors of the current one.
+            while let Some(&i) = ancestors.last() {
+                if item_path.starts_with(&results[i].0) {
+                    break;
+                }
+
+                ancestors.pop();
+            }
+
+            let mut materialized = match ancestors.last() {
+                Some(&i) => results[i].1.clone(),
+                None => Block::new(),
+            };
+            materialized.extend(block);
+
+            ancestors.push(results.len());
+            results.push((item_path, materialized));
+        }
+
+        Ok(results)
+    }
+
+    /// Processes metadata for every item file in a tree, and collects the
+    /// results into a single flat mapping, keyed by each item's path relative
+    /// to the root. Keys always use `/` as the path separator, regardless of
+    /// platform, and the root item itself is keyed by the empty string.
+    pub fn process_tree_to_flat_value(root: &Path, config: &Config) -> Result<Value, Error> {
+        let mut flat = Block::new();
+
+        for (item_path, block) in Self::process_tree(root, config, None)? {
+            // Items are always found under the root, so this cannot fail.
+            let rel_path = item_path.strip_prefix(root).unwrap_or(&item_path);
+
+            let key = rel_path
+                .components()
+                .map(|c| c.as_os_str().to_str())
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| Error::NonUnicodePath(item_path.clone()))?
+                .join("/");
+
+            flat.insert(key, Value::Mapping(block));
+        }
+
+        Ok(Value::Mapping(flat))
+    }
+
+    /// Processes metadata for every item file in a tree, similar to
+    /// `process_tree`, but does not stop at the first error. All results are
+    /// returned in walk order, along with summary counts of the scan.
+    pub fn process_tree_with_stats(
+        root: &Path,
+        config: &Config,
+    ) -> (Vec<ItemResult>, ProcessStats) {
+        let mut results = Vec::new();
+        let mut stats = ProcessStats::default();
+        let mut walker = ChildFileWalker::new(root, &config.selection, &config.sorter);
+        let cache = ProcessorCache::new();
+
+        // A broken index is reported once, and the scan goes on without it.
+        let indexed = Self::process_index(root, config).unwrap_or_else(|err| {
+            stats.errored += 1;
+            results.push(Err(err));
+            HashMap::new()
+        });
+
+        while let Some(walk_res) = walker.next() {
+            let item_path = match walk_res {
+                Ok(item_path) => item_path,
+                Err(err) => {
+                    stats.errored += 1;
+                    results.push(Err(Error::CannotWalkTree(err)));
+                    continue;
+                },
+            };
+
+            stats.selected += 1;
+
+            let block_res = Self::merge_item_file(
+                &item_path,
+                &config.sourcer,
+                &config.selection,
+                &config.sorter,
+                Some(&cache),
+                Some(&indexed),
+            );
+
+            match block_res {
+                Ok(block) => {
+                    stats.processed += 1;
+                    results.push(Ok((item_path.to_path_buf(), block)));
+                },
+                Err(err) => {
+                    stats.errored += 1;
+                    results.push(Err(err));
+                },
+            }
+
+            match walker.delve() {
+                Ok(_) => {
+                    stats.skipped += walker
+                        .rejected()
+                        .iter()
+                        .filter(|path| !Self::is_meta_or_hidden(path, &config.sourcer))
+                        .count();
+                },
+                Err(err) => {
+                    stats.errored += 1;
+                    results.push(Err(Error::CannotWalkTree(err)));
+                },
+            }
+        }
+
+        (results, stats)
+    }
+
+    /// Returns true if a path is a meta file or index file of a sourcer, or is
+    /// hidden, i.e. its name starts with `.`.
+    fn is_meta_or_hidden(path: &Path, sourcer: &Sourcer) -> bool {
+        let file_name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(file_name) => file_name,
+            None => return false,
+        };
+
+        file_name.starts_with('.')
+            || sourcer.as_sources().iter().chain(sourcer.index()).any(|source| source.name == file_name)
+    }
+
+    /// Walks and processes every item file in a tree, passing each result to
+    /// a callback. Walking stops early if the callback returns `false`.
+    /// The index of the root is loaded once up front, and meta files are read
+    /// through a cache that lives as long as the walk, so that each is only
+    /// processed once.
+    /// Walking stops with `Error::Cancelled` if the cancellation flag is set.
+    fn for_each_in_tree<F>(
+        root: &Path,
+        config: &Config,
+        cancel: Option<Arc<AtomicBool>>,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(PathBuf, Block) -> bool,
+    {
+        let mut walker = ChildFileWalker::new(root, &config.selection, &config.sorter);
+        if let Some(cancel) = cancel {
+            walker = walker.with_cancel(cancel);
+        }
+
+        let cache = ProcessorCache::new();
+        let indexed = Self::process_index(root, config)?;
+
+        while let Some(walk_res) = walker.next() {
+            let item_path = match walk_res {
+                Ok(item_path) => item_path,
+                Err(_) if walker.is_cancelled() => return Err(Error::Cancelled),
+                Err(err) => return Err(Error::CannotWalkTree(err)),
+            };
+
+            let block = Self::merge_item_file(
+                &item_path,
+                &config.sourcer,
+                &config.selection,
+                &config.sorter,
+                Some(&cache),
+                Some(&indexed),
+            )?;
+
+            if !f(item_path.to_path_buf(), block) {
+                break;
+            }
+
+            walker.delve().map_err(Error::CannotWalkTree)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Processor {
+    /// Asynchronous version of `process_tree`, that yields results as a stream.
+    /// The filesystem work is done on a blocking thread via `spawn_blocking`,
+    /// and results are sent back over a bounded channel, so this must be
+    /// called from within a Tokio runtime. The stream ends after the first
+    /// error. Dropping the stream stops the walk at the next item, and setting
+    /// the cancellation flag ends the stream with `Error::Cancelled`.
+    pub fn process_tree_stream(
+        root: PathBuf,
+        config: Arc<Config>,
+        cancel: Option<Arc<AtomicBool>>,
+    ) -> impl tokio_stream::Stream<Item = ItemResult> {
+        let (tx, rx) = tokio::sync::mpsc::channel(32);
+
+        tokio::task::spawn_blocking(move || {
+            let walk_res = Self::for_each_in_tree(&root, &config, cancel, |item_path, block| {
+                tx.blocking_send(Ok((item_path, block))).is_ok()
+            });
+
+            if let Err(err) = walk_res {
+                // The receiver may have been dropped, nothing to do then.
+                let _ = tx.blocking_send(Err(err));
+            }
+        });
+
+        tokio_stream::wrappers::ReceiverStream::new(rx)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Processor {
+    /// Parallel version of `process_tree_with_stats`, that processes each of
+    /// several directory trees on a Rayon thread pool.
+    /// The results of each tree are returned in the same order as the input
+    /// directories, and in walk order within each tree.
+    pub fn process_dirs_parallel(dirs: &[PathBuf], config: &Config) -> Vec<ItemResult> {
+        Self::process_dirs_parallel_with_stats(dirs, config).0
+    }
+
+    /// Similar to `process_dirs_parallel`, but also returns the summary counts
+    /// of all of the trees, summed together.
+    pub fn process_dirs_parallel_with_stats(dirs: &[PathBuf], config: &Config) -> (Vec<ItemResult>, ProcessStats) {
+        use rayon::prelude::*;
+
+        let per_dir: Vec<_> = dirs
+            .par_iter()
+            .map(|dir| Self::process_tree_with_stats(dir, config))
+            .collect();
+
+        let mut results = Vec::new();
+        let mut stats = ProcessStats::default();
+
+        for (dir_results, dir_stats) in per_dir {
+            results.extend(dir_results);
+            stats += dir_stats;
+        }
+
+        (results, stats)
+    }
+}
+
+/// Returns the directory that contains an item path, used as a grouping key.
+/// Paths without a parent (e.g. a filesystem root) are grouped under an empty
+/// path.
+fn parent_dir(item_path: &Path) -> PathBuf {
+    item_path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Iterator adaptor that batches consecutive processed items sharing the same
+/// parent directory. See `group_by_dir` for details.
+pub struct GroupByDir<I: Iterator<Item = (PathBuf, Block)>> {
+    inner: Peekable<I>,
+}
+
+impl<I> Iterator for GroupByDir<I>
+where
+    I: Iterator<Item = (PathBuf, Block)>,
+{
+    type Item = (PathBuf, Vec<(PathBuf, Block)>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.inner.next()?;
+        let dir = parent_dir(&first.0);
+
+        let mut group = vec![first];
+
+        while let Some((next_path, _)) = self.inner.peek() {
+            if parent_dir(next_path) != dir {
+                break;
+            }
+
+            // Safe to unwrap, since the peek was successful.
+            group.push(self.inner.next().unwrap());
+        }
+
+        Some((dir, group))
+    }
+}
+
+/// Batches consecutive processed items that share the same parent directory,
+/// yielding each directory path along with its items in their original order.
+/// Only adjacent items are grouped: this relies on the input emitting all of
+/// the items of a directory contiguously. If the items of a directory are
+/// interleaved with items from other directories, that directory will be
+/// yielded once per contiguous run.
+pub fn group_by_dir<I>(iter: I) -> GroupByDir<I::IntoIter>
+where
+    I: IntoIterator<Item = (PathBuf, Block)>,
+{
+    GroupByDir { inner: iter.into_iter().peekable() }
 }
 
 #[cfg(test)]
@@ -99,7 +780,7 @@ mod tests {
     use str_macro::str;
 
     use crate::config::selection::Matcher;
-    use crate::sources::Anchor;
+    use std::fs::File;
 
     use crate::test_util::TestUtil as TU;
 
@@ -186,6 +867,852 @@ mod tests {
         }
     }
 
+    #[test]
+    fn process_tree_with_progress() {
+        let temp_dir = TU::create_temp_media_test_dir("process_tree_with_progress");
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        let mut visited = Vec::new();
+
+        let produced = Processor::process_tree_with_progress(
+            path,
+            &config,
+            None,
+            |p| visited.push(p.to_owned()),
+        )
+        .unwrap();
+
+        // The default test library contains 44 items, plus the root itself.
+        assert_eq!(45, visited.len());
+        assert_eq!(visited, produced.into_iter().map(|(p, _)| p).collect::<Vec<_>>());
+
+        assert_eq!(visited[0], path);
+        assert_eq!(visited[1], path.join("ALBUM_01"));
+        assert_eq!(visited[2], path.join("ALBUM_01").join("DISC_01"));
+        assert_eq!(visited[3], path.join("ALBUM_01").join("DISC_01").join("TRACK_01.flac"));
+    }
+
+    #[test]
+    fn process_tree_cancel() {
+        let temp_dir = TU::create_temp_media_test_dir("process_tree_cancel");
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        // Cancelling partway stops before the next item.
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut num_visited = 0;
+
+        let produced = Processor::process_tree_with_progress(
+            path,
+            &config,
+            Some(Arc::clone(&cancel)),
+            |_| {
+                num_visited += 1;
+                if num_visited == 5 {
+                    cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+                }
+            },
+        );
+
+        assert!(matches!(produced, Err(Error::Cancelled)));
+        assert_eq!(5, num_visited);
+
+        // An already-set flag stops before the root.
+        let cancel = Arc::new(AtomicBool::new(true));
+        assert!(matches!(Processor::process_tree(path, &config, Some(cancel)), Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn process_index() {
+        let temp_dir = TU::create_temp_media_test_dir("process_index");
+        let path = temp_dir.path();
+
+        std::fs::write(path.join("index.yml"), r#"
+            ALBUM_01:
+                title: First album
+            ALBUM_01/DISC_01/TRACK_01.flac:
+                title: First track
+            ALBUM_02/TRACK_01.flac:
+                title: Another track
+        "#).unwrap();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            index = "index.yml"
+        "#).unwrap();
+
+        let expected = hashmap![
+            path.join("ALBUM_01") => Block(btreemap![
+                str!("title") => TU::s("First album"),
+            ]),
+            path.join("ALBUM_01").join("DISC_01").join("TRACK_01.flac") => Block(btreemap![
+                str!("title") => TU::s("First track"),
+            ]),
+            path.join("ALBUM_02").join("TRACK_01.flac") => Block(btreemap![
+                str!("title") => TU::s("Another track"),
+            ]),
+        ];
+        let produced = Processor::process_index(path, &config).unwrap();
+        assert_eq!(expected, produced);
+
+        // No index configured.
+        assert!(Processor::process_index(path, &Config::default()).unwrap().is_empty());
+
+        // Keys must refer to existing items, and be plain relative paths.
+        std::fs::write(path.join("index.yml"), "ALBUM_99:\n  title: Missing\n").unwrap();
+        assert!(matches!(
+            Processor::process_index(path, &config),
+            Err(Error::MissingIndexTarget(..))
+        ));
+
+        std::fs::write(path.join("index.yml"), "../ALBUM_01:\n  title: Outside\n").unwrap();
+        assert!(matches!(
+            Processor::process_index(path, &config),
+            Err(Error::InvalidIndexKey(..))
+        ));
+
+        // Index must be a mapping.
+        std::fs::write(path.join("index.yml"), "- title: Unkeyed\n").unwrap();
+        assert!(matches!(
+            Processor::process_index(path, &config),
+            Err(Error::InvalidIndex)
+        ));
+
+        // Keys must refer to selected items, including their parent directories.
+        let config = Config::from_str(r#"
+            [filtering]
+            exclude_dirs = ["DISC_01"]
+            [sourcing]
+            index = "index.yml"
+        "#).unwrap();
+
+        std::fs::write(path.join("index.yml"), "ALBUM_01/DISC_01/TRACK_01.flac:\n  title: Unselected\n").unwrap();
+        assert!(matches!(
+            Processor::process_index(path, &config),
+            Err(Error::PlexerError(PlexerError::UnusedTaggedBlock(_, key))) if key == "ALBUM_01/DISC_01/TRACK_01.flac"
+        ));
+    }
+
+    #[test]
+    fn process_item_file_indexed() {
+        let temp_dir = TU::create_temp_media_test_dir("process_item_file_indexed");
+        let path = temp_dir.path();
+
+        std::fs::write(path.join("index.yml"), r#"
+            ALBUM_01/DISC_01/TRACK_01.flac:
+                title: First track
+                overridden: index
+        "#).unwrap();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+            index = "index.yml"
+        "#).unwrap();
+
+        let indexed = Processor::process_index(path, &config).unwrap();
+
+        let item_path = path.join("ALBUM_01").join("DISC_01").join("TRACK_01.flac");
+
+        let produced = Processor::process_item_file_indexed(
+            &item_path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+            &indexed,
+        )
+        .unwrap();
+
+        // Meta files override index entries.
+        assert_eq!(Some(&TU::s("First track")), produced.get("title"));
+        assert_eq!(Some(&TU::s("TRACK_01_item")), produced.get("overridden"));
+
+        // Without the index, only meta files are used.
+        let produced = Processor::process_item_file(
+            &item_path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+        )
+        .unwrap();
+
+        assert_eq!(None, produced.get("title"));
+
+        // Items without index entries are unaffected.
+        let item_path = path.join("ALBUM_01").join("DISC_01").join("TRACK_02.flac");
+
+        let produced = Processor::process_item_file_indexed(
+            &item_path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+            &indexed,
+        )
+        .unwrap();
+
+        assert_eq!(None, produced.get("title"));
+    }
+
+    #[test]
+    fn process_tree_with_index() {
+        let temp_dir = TU::create_temp_media_test_dir("process_tree_with_index");
+        let path = temp_dir.path();
+
+        std::fs::write(path.join("index.yml"), r#"
+            ALBUM_01:
+                title: First album
+            ALBUM_01/DISC_01/TRACK_01.flac:
+                title: First track
+        "#).unwrap();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+            index = "index.yml"
+        "#).unwrap();
+
+        let produced = Processor::process_tree(path, &config, None).unwrap();
+
+        // The index file itself is not an item.
+        assert_eq!(45, produced.len());
+
+        let titled = produced
+            .iter()
+            .filter_map(|(item_path, block)| block.get("title").map(|title| (item_path.clone(), title.clone())))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (path.join("ALBUM_01"), TU::s("First album")),
+                (path.join("ALBUM_01").join("DISC_01").join("TRACK_01.flac"), TU::s("First track")),
+            ],
+            titled,
+        );
+
+        // Entries for unselected items fail the walk.
+        let config = Config::from_str(r#"
+            [filtering]
+            exclude_dirs = ["DISC_01"]
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+            index = "index.yml"
+        "#).unwrap();
+
+        assert!(matches!(
+            Processor::process_tree(path, &config, None),
+            Err(Error::PlexerError(PlexerError::UnusedTaggedBlock(..)))
+        ));
+
+        // Index files outside of the library root are ignored, even if broken.
+        std::fs::write(path.join("index.yml"), "- not: a mapping\n").unwrap();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+            index = "index.yml"
+        "#).unwrap();
+
+        let library_path = path.join("ALBUM_01");
+        let produced = Processor::process_tree(&library_path, &config, None).unwrap();
+        assert!(produced.iter().all(|(_, block)| block.get("title").is_none()));
+
+        let produced = Processor::materialize_item(&library_path, &library_path.join("DISC_01"), &config).unwrap();
+        assert_eq!(None, produced.get("title"));
+
+        // An index file in a subdirectory of the root is not used either.
+        std::fs::write(library_path.join("index.yml"), "DISC_01/TRACK_01.flac:\n  title: Nested\n").unwrap();
+
+        std::fs::remove_file(path.join("index.yml")).unwrap();
+
+        let produced = Processor::process_tree(path, &config, None).unwrap();
+        assert!(produced.iter().all(|(_, block)| block.get("title").is_none()));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn process_dirs_parallel() {
+        let temp_dir = TU::create_meta_fanout_test_dir("process_dirs_parallel", 3, 3, |_, _| false);
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        // Include a corrupt directory, to check that errors stay in place.
+        std::fs::write(path.join("1").join("self.json"), "not valid json").unwrap();
+
+        let dirs = vec![path.join("2"), path.join("0"), path.join("1"), path.join("0")];
+
+        let summarize = |results: Vec<ItemResult>| {
+            results.into_iter()
+                .map(|res| res.map_err(|e| e.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut expected_results = Vec::new();
+        let mut expected_stats = ProcessStats::default();
+
+        for dir in &dirs {
+            let (dir_results, dir_stats) = Processor::process_tree_with_stats(dir, &config);
+            expected_results.extend(dir_results);
+            expected_stats += dir_stats;
+        }
+
+        let expected = summarize(expected_results);
+        let produced = summarize(Processor::process_dirs_parallel(&dirs, &config));
+
+        assert_eq!(expected.len(), 4 * 40);
+        assert!(expected.iter().any(Result::is_err));
+        assert_eq!(expected, produced);
+
+        let (produced_results, produced_stats) = Processor::process_dirs_parallel_with_stats(&dirs, &config);
+
+        assert_eq!(expected, summarize(produced_results));
+        assert_eq!(expected_stats, produced_stats);
+        assert_eq!(4 * 40, produced_stats.selected);
+        assert!(produced_stats.errored > 0);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn process_tree_stream() {
+        use tokio_stream::StreamExt;
+
+        let temp_dir = TU::create_temp_media_test_dir("process_tree_stream");
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        let expected = Processor::process_tree(path, &config, None).unwrap();
+
+        let produced = Processor::process_tree_stream(path.to_path_buf(), Arc::new(config), None)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(45, produced.len());
+        assert_eq!(expected, produced);
+
+        // An already-set flag ends the stream with a cancellation error.
+        let cancel = Arc::new(AtomicBool::new(true));
+        let config = Arc::new(Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap());
+
+        let produced = Processor::process_tree_stream(path.to_path_buf(), config, Some(cancel))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(1, produced.len());
+        assert!(matches!(produced[0], Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn process_tree_skip_dir_if_contains() {
+        let temp_dir = TU::create_plain_fanout_test_dir("process_tree_skip_dir_if_contains", 3, 2);
+        let path = temp_dir.path();
+
+        let skipped_dir = path.join("1").join("1_2");
+        std::fs::File::create(skipped_dir.join(".noindex")).unwrap();
+
+        let config = Config::from_str(r#"
+            [filtering]
+            skip_dir_if_contains = [".noindex"]
+            [sourcing]
+            track = []
+            album = []
+        "#).unwrap();
+
+        let produced = Processor::process_tree(path, &config, None).unwrap();
+
+        // The root, 3 + 9 dirs, and 27 files, minus the skipped dir and its 3 files.
+        assert_eq!(1 + 3 + 9 + 27 - 4, produced.len());
+        assert!(produced.iter().any(|(p, _)| p == &path.join("1").join("1_1")));
+        assert!(produced.iter().all(|(p, _)| !p.starts_with(&skipped_dir)));
+    }
+
+    #[test]
+    fn process_tree_to_flat_value() {
+        let temp_dir = TU::create_plain_fanout_test_dir("process_tree_to_flat_value", 2, 1);
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = []
+            album = []
+        "#).unwrap();
+
+        let empty = Value::Mapping(Block::new());
+        let expected = Value::Mapping(Block(btreemap![
+            str!("") => empty.clone(),
+            str!("0") => empty.clone(),
+            str!("0/0_0") => empty.clone(),
+            str!("0/0_1") => empty.clone(),
+            str!("1") => empty.clone(),
+            str!("1/1_0") => empty.clone(),
+            str!("1/1_1") => empty.clone(),
+        ]));
+        let produced = Processor::process_tree_to_flat_value(path, &config).unwrap();
+        assert_eq!(expected, produced);
+    }
+
+    #[test]
+    fn process_tree_with_stats() {
+        let temp_dir = TU::create_plain_fanout_test_dir("process_tree_with_stats", 3, 2);
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [filtering]
+            exclude_files = ["*_2", ".*"]
+            [sourcing]
+            track = []
+            album = ["self.json"]
+        "#).unwrap();
+
+        // Meta files and hidden files are not counted as skipped items.
+        std::fs::write(path.join("0").join("self.json"), "{}").unwrap();
+        std::fs::write(path.join("0").join(".hidden"), "").unwrap();
+
+        let (results, stats) = Processor::process_tree_with_stats(path, &config);
+
+        // The root, 3 + 9 dirs, and 27 files, of which 9 files are excluded.
+        let expected = ProcessStats {
+            selected: 1 + 3 + 9 + 27 - 9,
+            processed: 1 + 3 + 9 + 27 - 9,
+            skipped: 9,
+            errored: 0,
+        };
+        assert_eq!(expected, stats);
+        assert_eq!(1 + 3 + 9 + 27, stats.selected + stats.skipped);
+        assert_eq!(stats.selected, results.len());
+        assert!(results.iter().all(Result::is_ok));
+
+        // Errors are counted, and do not stop the scan.
+        let temp_dir = TU::create_temp_media_test_dir("process_tree_with_stats");
+        let path = temp_dir.path();
+
+        std::fs::write(path.join("ALBUM_01").join("self.json"), "not json").unwrap();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        let (results, stats) = Processor::process_tree_with_stats(path, &config);
+
+        assert_eq!(45, stats.selected);
+        assert!(stats.errored > 0);
+        assert_eq!(stats.selected, stats.processed + stats.errored);
+        assert_eq!(stats.errored, results.iter().filter(|r| r.is_err()).count());
+    }
+
+    #[test]
+    fn process_item_file_multi_format() {
+        let temp_dir = TU::create_temp_media_test_dir("process_item_file_multi_format");
+        let path = temp_dir.path();
+
+        std::fs::write(path.join("self.yml"), "overridden: ROOT_self_yml\nyml_key: yml_val\n").unwrap();
+
+        let json_block = Block(btreemap![
+            str!("ROOT_self_key") => TU::s("ROOT_self_val"),
+            str!("const_key") => TU::s("const_val"),
+            str!("self_key") => TU::s("self_val"),
+            str!("overridden") => TU::s("ROOT_self"),
+        ]);
+        let yaml_block = Block(btreemap![
+            str!("overridden") => TU::s("ROOT_self_yml"),
+            str!("yml_key") => TU::s("yml_val"),
+        ]);
+        let mut merged_block = json_block.clone();
+        merged_block.extend(yaml_block.clone());
+
+        let inputs_and_expected = vec![
+            ("prefer_yaml", Some(yaml_block)),
+            ("prefer_json", Some(json_block)),
+            ("merge_prefer_later", Some(merged_block)),
+            ("error", None),
+        ];
+
+        for (policy, expected) in inputs_and_expected {
+            let config = Config::from_str(&format!(r#"
+                [sourcing]
+                track = []
+                album = ["self.json", "self.yml"]
+                multi_format = "{}"
+            "#, policy)).unwrap();
+
+            let produced = Processor::process_item_file(
+                path,
+                &config.sourcer,
+                &config.selection,
+                &config.sorter,
+            );
+
+            match expected {
+                Some(expected) => assert_eq!(expected, produced.unwrap()),
+                None => assert!(matches!(
+                    produced,
+                    Err(Error::CannotFindMetaPath(SourceError::MultiFormatConflict(..)))
+                )),
+            }
+        }
+
+        // Only one format present, so every policy uses it.
+        let path = path.join("ALBUM_01");
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = []
+            album = ["self.json", "self.yml"]
+            multi_format = "error"
+        "#).unwrap();
+        assert!(Processor::process_item_file(
+            &path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+        ).is_ok());
+
+        // TOML meta files are routed to the TOML reader. Preferring a format
+        // that is not present uses all of the formats that are.
+        std::fs::write(path.join("self.toml"), "overridden = \"ALBUM_01_self_toml\"\ntoml_key = 1\n").unwrap();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = []
+            album = ["self.json", "self.yml", "self.toml"]
+            multi_format = "prefer_yaml"
+        "#).unwrap();
+        let produced = Processor::process_item_file(
+            &path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+        ).unwrap();
+        assert_eq!(produced.get("overridden"), Some(&TU::s("ALBUM_01_self_toml")));
+        assert_eq!(produced.get("toml_key"), Some(&TU::i(1)));
+        assert_eq!(produced.get("ALBUM_01_self_key"), Some(&TU::s("ALBUM_01_self_val")));
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = []
+            album = ["self.json", "self.yml", "self.toml"]
+            multi_format = "prefer_toml"
+        "#).unwrap();
+        let produced = Processor::process_item_file(
+            &path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+        ).unwrap();
+        assert_eq!(produced.get("toml_key"), Some(&TU::i(1)));
+        assert_eq!(produced.get("ALBUM_01_self_key"), None);
+
+        // Merged formats are combined deeply, key by key.
+        std::fs::write(path.join("self.json"), r#"{"credits": {"artist": "json_artist", "label": "json_label"}}"#).unwrap();
+        std::fs::write(path.join("self.yml"), "credits:\n  artist: yml_artist\n").unwrap();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = []
+            album = ["self.json", "self.yml"]
+            multi_format = "merge_prefer_later"
+        "#).unwrap();
+        let produced = Processor::process_item_file(
+            &path,
+            &config.sourcer,
+            &config.selection,
+            &config.sorter,
+        ).unwrap();
+        assert_eq!(
+            produced.get("credits"),
+            Some(&Value::Mapping(Block(btreemap![
+                str!("artist") => TU::s("yml_artist"),
+                str!("label") => TU::s("json_label"),
+            ]))),
+        );
+
+        // Conflicts are reported once per pair of meta files.
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = []
+            album = ["self.json", "self.yml"]
+            multi_format = "error"
+        "#).unwrap();
+        let produced = config.sourcer.meta_paths(&path).collect::<Vec<_>>();
+        assert_eq!(1, produced.len());
+        assert!(matches!(
+            &produced[0],
+            Err(SourceError::MultiFormatConflict(meta_path, sibling_path))
+                if meta_path == &path.join("self.json") && sibling_path == &path.join("self.yml")
+        ));
+    }
+
+    #[test]
+    fn stream_dir() {
+        let temp_dir = TU::create_meta_fanout_test_dir("stream_dir", 3, 1, |_, _| false);
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        let target_name = |block: &Block| block.get("target_file_name").cloned();
+
+        let produced = Processor::stream_dir(path, &config)
+            .map(|res| res.map(|(p, b)| (p, target_name(&b))))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected = vec![
+            (path.join("0"), Some(TU::s("0"))),
+            (path.join("1"), Some(TU::s("1"))),
+            (path.join("2"), Some(TU::s("2"))),
+        ];
+        assert_eq!(expected, produced);
+
+        // Items are yielded lazily, one at a time.
+        let mut stream = Processor::stream_dir(path, &config);
+        assert_eq!(stream.next().unwrap().unwrap().0, path.join("0"));
+
+        // Plexing errors are yielded as items, and do not stop the stream.
+        std::fs::write(path.join("3"), "").unwrap();
+        let produced = Processor::stream_dir(path, &config).collect::<Vec<_>>();
+        assert_eq!(produced.len(), 4);
+        assert_eq!(produced.iter().filter(|res| res.is_ok()).count(), 3);
+        assert!(matches!(produced[3], Err(Error::PlexerError(PlexerError::UnusedItemPath(..)))));
+
+        // Unreadable meta files are reported as errors.
+        let sub_path = path.join("0");
+        std::fs::write(sub_path.join("item.json"), "not valid json").unwrap();
+        let produced = Processor::stream_dir(&sub_path, &config).collect::<Vec<_>>();
+        assert_eq!(produced.len(), 1);
+        assert!(matches!(produced[0], Err(Error::CannotReadMetadata(..))));
+
+        // A directory without any external meta files yields nothing.
+        let sub_path = path.join("1").join("1_0");
+        assert_eq!(Processor::stream_dir(&sub_path, &config).count(), 0);
+    }
+
+    #[test]
+    fn materialize_tree_mixed_formats() {
+        let temp_dir = TU::create_temp_media_test_dir("materialize_tree_mixed_formats");
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json", "item.yml"]
+            album = ["self.json", "self.yml"]
+        "#).unwrap();
+
+        let expected = Processor::materialize_tree(path, &config).unwrap();
+
+        // Convert the meta files of one album to YAML, the rest stay as JSON.
+        let album_path = path.join("ALBUM_02");
+        for stub in &["self", "item"] {
+            let json_path = album_path.join(format!("{}.json", stub));
+            let value = Format::Json.read_raw_path(&json_path).unwrap();
+
+            std::fs::write(album_path.join(format!("{}.yml", stub)), Format::Yaml.write_raw_str(&value).unwrap()).unwrap();
+            std::fs::remove_file(json_path).unwrap();
+        }
+
+        // Each meta file is read using the format of its extension.
+        let produced = Processor::materialize_tree(path, &config).unwrap();
+        assert_eq!(expected, produced);
+
+        let item_path = album_path.join("DISC_01").join("TRACK_01.flac");
+        let produced = Processor::materialize_item(path, &item_path, &config).unwrap();
+        assert_eq!(produced.get("ALBUM_02_self_key"), Some(&TU::s("ALBUM_02_self_val")));
+        assert_eq!(produced.get("DISC_01_self_key"), Some(&TU::s("DISC_01_self_val")));
+        assert_eq!(produced.get("ROOT_self_key"), Some(&TU::s("ROOT_self_val")));
+    }
+
+    #[test]
+    fn materialize_item() {
+        let temp_dir = TU::create_temp_media_test_dir("materialize_item");
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        let item_path = path.join("ALBUM_01").join("DISC_01").join("TRACK_01.flac");
+
+        let produced = Processor::materialize_item(path, &item_path, &config).unwrap();
+
+        // Inherited from the root and album.
+        assert_eq!(produced.get("ROOT_self_key"), Some(&TU::s("ROOT_self_val")));
+        assert_eq!(produced.get("ALBUM_01_self_key"), Some(&TU::s("ALBUM_01_self_val")));
+        assert_eq!(produced.get("DISC_01_self_key"), Some(&TU::s("DISC_01_self_val")));
+
+        // Defined on the item itself, overriding inherited values.
+        assert_eq!(produced.get("TRACK_01_item_key"), Some(&TU::s("TRACK_01_item_val")));
+        assert_eq!(produced.get("overridden"), Some(&TU::s("TRACK_01_item")));
+
+        // Siblings do not contribute.
+        assert_eq!(produced.get("ALBUM_02_item_key"), None);
+
+        // The root itself has just its own metadata.
+        assert_eq!(
+            Processor::materialize_item(path, path, &config).unwrap(),
+            Processor::process_item_file(path, &config.sourcer, &config.selection, &config.sorter).unwrap(),
+        );
+
+        assert!(matches!(
+            Processor::materialize_item(&item_path, path, &config),
+            Err(Error::NotUnderRoot(..))
+        ));
+
+        // The tree variant agrees with the per-item one.
+        let produced = Processor::materialize_tree(path, &config).unwrap();
+        assert_eq!(45, produced.len());
+
+        for (item_path, block) in produced {
+            assert_eq!(Processor::materialize_item(path, &item_path, &config).unwrap(), block);
+        }
+    }
+
+    #[test]
+    fn group_by_dir() {
+        let temp_dir = TU::create_plain_fanout_test_dir("group_by_dir", 3, 2);
+        let path = temp_dir.path();
+
+        let selection = Selection::default();
+        let sorter = Sorter::default();
+
+        // Only keep the leaf files, which are emitted contiguously per
+        // directory by a depth-first walk.
+        let mut items = Vec::new();
+        let mut walker = ChildFileWalker::new(path, &selection, &sorter);
+
+        while let Some(res) = walker.next() {
+            let item_path = res.unwrap();
+
+            if item_path.is_file() {
+                items.push((item_path.into_owned(), Block::new()));
+            }
+
+            walker.delve().unwrap();
+        }
+
+        assert_eq!(27, items.len());
+
+        let produced = super::group_by_dir(items).collect::<Vec<_>>();
+
+        // There are 9 leaf directories, each containing 3 files.
+        assert_eq!(9, produced.len());
+
+        let mut seen_dirs = std::collections::HashSet::new();
+        for (dir, group) in produced {
+            assert!(seen_dirs.insert(dir.clone()));
+            assert_eq!(3, group.len());
+
+            for (item_path, _) in group {
+                assert_eq!(Some(dir.as_path()), item_path.parent());
+            }
+        }
+
+        assert!(seen_dirs.contains(&path.join("0").join("0_1")));
+
+        // Interleaved directories are yielded once per contiguous run.
+        let interleaved = vec![
+            (PathBuf::from("a/1"), Block::new()),
+            (PathBuf::from("a/2"), Block::new()),
+            (PathBuf::from("b/1"), Block::new()),
+            (PathBuf::from("a/3"), Block::new()),
+        ];
+        let produced = super::group_by_dir(interleaved)
+            .map(|(dir, group)| (dir, group.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (PathBuf::from("a"), 2),
+                (PathBuf::from("b"), 1),
+                (PathBuf::from("a"), 1),
+            ],
+            produced,
+        );
+    }
+
+    #[test]
+    fn process_item_file_cached() {
+        let temp_dir = TU::create_simple_dir("process_item_file_cached", ["a.flac", "b.flac"]);
+        let path = temp_dir.path();
+        let meta_path = path.join("item.yml");
+
+        let selection = Selection::new(
+            Matcher::any(),
+            Matcher::build(&["*.yml"]).unwrap(),
+            Matcher::any(),
+            Matcher::empty(),
+        );
+        let sorter = Sorter::default();
+        let mut sourcer = Sourcer::new();
+        sourcer.source(Source::from_name(str!("item.yml"), Anchor::External).unwrap());
+
+        let cache = ProcessorCache::new();
+        let get = |name: &str| {
+            Processor::process_item_file_cached(&path.join(name), &sourcer, &selection, &sorter, &cache)
+                .unwrap()
+                .get("key")
+                .cloned()
+        };
+
+        std::fs::write(&meta_path, "- key: a_1\n- key: b_1").unwrap();
+        let mtime = std::fs::metadata(&meta_path).unwrap().modified().unwrap();
+
+        assert_eq!(get("a.flac"), Some(TU::s("a_1")));
+        assert_eq!(get("b.flac"), Some(TU::s("b_1")));
+        assert_eq!(cache.entries.borrow().len(), 1);
+
+        // Changes that leave the modification time as-is are not picked up.
+        std::fs::write(&meta_path, "- key: a_2\n- key: b_2").unwrap();
+        File::options().write(true).open(&meta_path).unwrap().set_modified(mtime).unwrap();
+        assert_eq!(get("a.flac"), Some(TU::s("a_1")));
+
+        // A new modification time causes the meta file to be reloaded.
+        let new_mtime = mtime + std::time::Duration::from_secs(10);
+        File::options().write(true).open(&meta_path).unwrap().set_modified(new_mtime).unwrap();
+        assert_eq!(get("a.flac"), Some(TU::s("a_2")));
+        assert_eq!(get("b.flac"), Some(TU::s("b_2")));
+        assert_eq!(cache.entries.borrow().len(), 1);
+
+        // Errors are not cached.
+        std::fs::write(&meta_path, "- key: [unclosed").unwrap();
+        let res = Processor::process_item_file_cached(&path.join("a.flac"), &sourcer, &selection, &sorter, &cache);
+        assert!(matches!(res, Err(Error::CannotReadMetadata(..))));
+
+        cache.clear();
+        assert!(cache.entries.borrow().is_empty());
+    }
+
     #[test]
     fn process_item_file() {
         let temp_dir = TU::create_temp_media_test_dir("process_item_file");
@@ -51,6 +51,7 @@ impl<'a> From<&'a Anchor> for &'a Arity {
 /// A data structure-level representation of all metadata structures.
 /// This is intended to be agnostic to the text-level format of the metadata.
 #[derive(Debug, Clone, Serialize)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
 #[serde(untagged)]
 pub enum Schema {
     One(Block),
@@ -58,6 +59,23 @@ pub enum Schema {
     Map(BlockMap),
 }
 
+impl Schema {
+    /// Applies a function to every block contained in this schema, while
+    /// keeping the overall shape (and any map keys) of the schema intact.
+    pub fn map_blocks<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(Block) -> Block,
+    {
+        match self {
+            Self::One(block) => Self::One(f(block)),
+            Self::Seq(block_seq) => Self::Seq(block_seq.into_iter().map(f).collect()),
+            Self::Map(block_map) => Self::Map(
+                block_map.into_iter().map(|(name, block)| (name, f(block))).collect()
+            ),
+        }
+    }
+}
+
 impl From<SchemaRepr> for Schema {
     fn from(msr: SchemaRepr) -> Self {
         match msr {
@@ -67,3 +85,53 @@ impl From<SchemaRepr> for Schema {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indexmap::indexmap;
+    use maplit::btreemap;
+    use str_macro::str;
+
+    use crate::test_util::TestUtil as TU;
+
+    fn rename_keys(block: Block) -> Block {
+        block.into_iter().map(|(k, v)| (k.to_uppercase(), v)).collect()
+    }
+
+    #[test]
+    fn map_blocks() {
+        let block_a = Block(btreemap![str!("key_a") => TU::s("val_a")]);
+        let block_b = Block(btreemap![str!("key_b") => TU::s("val_b")]);
+
+        let renamed_a = Block(btreemap![str!("KEY_A") => TU::s("val_a")]);
+        let renamed_b = Block(btreemap![str!("KEY_B") => TU::s("val_b")]);
+
+        let inputs_and_expected = vec![
+            (
+                Schema::One(block_a.clone()),
+                Schema::One(renamed_a.clone()),
+            ),
+            (
+                Schema::Seq(BlockSeq(vec![block_a.clone(), block_b.clone()])),
+                Schema::Seq(BlockSeq(vec![renamed_a.clone(), renamed_b.clone()])),
+            ),
+            (
+                Schema::Map(BlockMap(indexmap![
+                    str!("name_b") => block_b.clone(),
+                    str!("name_a") => block_a.clone(),
+                ])),
+                Schema::Map(BlockMap(indexmap![
+                    str!("name_b") => renamed_b.clone(),
+                    str!("name_a") => renamed_a.clone(),
+                ])),
+            ),
+        ];
+
+        for (input, expected) in inputs_and_expected {
+            let produced = input.map_blocks(rename_keys);
+            assert_eq!(expected, produced);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Read-only overviews of the metadata in a directory tree.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::metadata::processor::Processor;
+
+/// An overview of the metadata of the item files in a directory tree, as
+/// produced by `summarize`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// Number of item paths that were selected and visited, including the root.
+    pub num_items: usize,
+    /// Number of item paths that were processed and had non-empty metadata.
+    pub num_with_metadata: usize,
+    /// Number of errors encountered, either while walking or processing.
+    pub num_errors: usize,
+    /// The distinct top-level metadata keys across all item paths.
+    pub keys: BTreeSet<String>,
+    /// For each top-level metadata key, the number of item paths that have it.
+    pub key_counts: BTreeMap<String, usize>,
+}
+
+/// Summarizes the metadata of every item file in a directory tree, using
+/// the selection, sorting, and sourcing rules of a `Config`.
+/// Items that fail to be processed are counted as errors, and do not stop
+/// the summary.
+pub fn summarize(dir: &Path, config: &Config) -> Summary {
+    let (results, stats) = Processor::process_tree_with_stats(dir, config);
+
+    let mut summary = Summary {
+        num_items: stats.selected,
+        num_errors: stats.errored,
+        ..Summary::default()
+    };
+
+    for (_, block) in results.iter().filter_map(|res| res.as_ref().ok()) {
+        if block.is_empty() {
+            continue;
+        }
+
+        summary.num_with_metadata += 1;
+
+        for key in block.keys() {
+            *summary.key_counts.entry(key.clone()).or_insert(0) += 1;
+        }
+    }
+
+    summary.keys = summary.key_counts.keys().cloned().collect();
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use maplit::btreemap;
+    use str_macro::str;
+
+    use crate::test_util::TestUtil as TU;
+
+    #[test]
+    fn summarize() {
+        // Only the first file of each directory gets a flag key.
+        let temp_dir = TU::create_meta_fanout_test_dir(
+            "summarize",
+            3,
+            1,
+            |depth_left, fanout_index| depth_left == 0 && fanout_index == 0,
+        );
+        let path = temp_dir.path();
+
+        let config = Config::from_str(r#"
+            [sourcing]
+            track = ["item.json"]
+            album = ["self.json"]
+        "#).unwrap();
+
+        let produced = super::summarize(path, &config);
+
+        // The root, 3 directories, and 9 files.
+        assert_eq!(produced.num_items, 13);
+        assert_eq!(produced.num_with_metadata, 13);
+        assert_eq!(produced.num_errors, 0);
+
+        let expected_key_counts = btreemap![
+            str!(TU::STRING_KEY) => 13,
+            str!(TU::INTEGER_KEY) => 13,
+            str!(TU::DECIMAL_KEY) => 13,
+            str!(TU::BOOLEAN_KEY) => 13,
+            str!(TU::NULL_KEY) => 13,
+            str!(TU::SEQUENCE_KEY) => 13,
+            str!(TU::MAPPING_KEY) => 13,
+            str!("anchor") => 13,
+            str!("target_file_name") => 13,
+            // The root and directories have self metadata.
+            str!("self_key") => 4,
+            // The directories and files have item metadata.
+            str!("item_key") => 12,
+            str!("flag_key") => 3,
+        ];
+        assert_eq!(produced.key_counts, expected_key_counts);
+        assert_eq!(produced.keys, expected_key_counts.keys().cloned().collect());
+
+        // Processing errors are counted, and do not stop the summary.
+        std::fs::write(path.join("0").join("item.json"), "not valid json").unwrap();
+
+        let produced = super::summarize(path, &config);
+        assert_eq!(produced.num_items, 13);
+        assert_eq!(produced.num_errors, 3);
+        assert_eq!(produced.num_with_metadata, 10);
+        assert_eq!(produced.key_counts.get("flag_key"), Some(&2));
+    }
+}
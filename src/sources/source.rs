@@ -1,7 +1,6 @@
 use std::borrow::Cow;
 use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
 
 use thiserror::Error;
 
@@ -38,6 +37,8 @@ pub enum SourceError {
 
     #[error("unable to read item directory: {0}")]
     IterDir(#[source] IoError),
+    #[error("meta files in multiple formats found: {}, {}", .0.display(), .1.display())]
+    MultiFormatConflict(PathBuf, PathBuf),
     // #[error("unable to read item directory entry: {0}")]
     // IterDirEntry(#[source] IoError),
 }
@@ -91,9 +92,9 @@ impl Source {
             None => { return Err(CreateError::MissingExt(name)); },
         };
 
-        let format = match Format::from_str(ext) {
-            Ok(fmt) => fmt,
-            Err(_) => { return Err(CreateError::UnknownExt(name)); },
+        let format = match Format::from_extension(ext) {
+            Some(fmt) => fmt,
+            None => { return Err(CreateError::UnknownExt(name)); },
         };
 
         Ok(Self { name, anchor, format, })
@@ -4,16 +4,61 @@ pub use self::item_paths::*;
 
 use std::path::{Path, PathBuf};
 
-use crate::sources::{Source, SourceError};
+use serde::Deserialize;
+
+use crate::config::Format;
+use crate::sources::{Anchor, Source, SourceError};
+
+/// Represents how to handle meta files for the same source name in different
+/// formats (e.g. "item.yml" and "item.json") that are both present, such as
+/// in the middle of a migration. This only has an effect if sources for more
+/// than one format are configured.
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MultiFormat {
+    /// Only use the YAML meta file.
+    PreferYaml,
+    /// Only use the JSON meta file.
+    PreferJson,
+    /// Only use the TOML meta file.
+    PreferToml,
+    /// Use both meta files, with the later configured source deep-merged into
+    /// the earlier one: nested mappings are combined key by key, and any other
+    /// values of the later source overwrite those of the earlier one.
+    #[default]
+    MergePreferLater,
+    /// Report an error.
+    Error,
+}
 
 // Represents an ordered collection of `Source`s, designed to find meta files
-// for a target item path.
+// for a target item path, along with an optional index source.
 #[derive(Debug)]
-pub struct Sourcer(Vec<Source>);
+pub struct Sourcer(Vec<Source>, MultiFormat, Option<Source>);
 
 impl Sourcer {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self(Vec::new(), MultiFormat::default(), None)
+    }
+
+    pub fn with_multi_format(mut self, multi_format: MultiFormat) -> Self {
+        self.1 = multi_format;
+        self
+    }
+
+    pub fn multi_format(&self) -> MultiFormat {
+        self.1
+    }
+
+    /// Sets the source of index meta files, which map item paths relative to
+    /// the directory containing the index file to blocks of metadata.
+    pub fn with_index(mut self, index: Option<Source>) -> Self {
+        self.2 = index;
+        self
+    }
+
+    pub fn index(&self) -> Option<&Source> {
+        self.2.as_ref()
     }
 
     pub fn source(&mut self, source: Source) -> &mut Self {
@@ -23,7 +68,9 @@ impl Sourcer {
 
     pub fn meta_paths<'a>(&'a self, item_path: &'a Path) -> MetaPaths<'a> {
         MetaPaths {
-            iter: self.0.iter(),
+            iter: self.0.iter().enumerate(),
+            sources: &self.0,
+            multi_format: self.1,
             item_path,
         }
     }
@@ -35,25 +82,90 @@ impl Sourcer {
 
 impl From<Vec<Source>> for Sourcer {
     fn from(value: Vec<Source>) -> Self {
-        Self(value)
+        Self(value, MultiFormat::default(), None)
     }
 }
 
+/// Returns true if two sources share an anchor and name stem, but differ in
+/// format, e.g. "item.yml" and "item.json".
+pub(crate) fn is_format_sibling(a: &Source, b: &Source) -> bool {
+    let same_anchor = matches!(
+        (a.anchor, b.anchor),
+        (Anchor::External, Anchor::External) | (Anchor::Internal, Anchor::Internal)
+    );
+
+    let stem = |s: &'_ Source| s.name.rsplitn(2, '.').last().map(String::from);
+
+    same_anchor && a.format != b.format && stem(a) == stem(b)
+}
+
 pub struct MetaPaths<'a> {
-    iter: std::slice::Iter<'a, Source>,
+    iter: std::iter::Enumerate<std::slice::Iter<'a, Source>>,
+    sources: &'a [Source],
+    multi_format: MultiFormat,
     item_path: &'a Path,
 }
 
+impl<'a> MetaPaths<'a> {
+    /// Applies the multi-format policy to a found meta path.
+    /// Returns `None` if the meta path should be skipped.
+    fn apply_policy(
+        &self,
+        meta_path: PathBuf,
+        source_idx: usize,
+        source: &'a Source,
+    ) -> Option<Result<(PathBuf, &'a Source), SourceError>> {
+        let preferred = match self.multi_format {
+            MultiFormat::MergePreferLater => return Some(Ok((meta_path, source))),
+            MultiFormat::PreferYaml => Some(Format::Yaml),
+            MultiFormat::PreferJson => Some(Format::Json),
+            MultiFormat::PreferToml => Some(Format::Toml),
+            MultiFormat::Error => None,
+        };
+
+        // Find any sibling meta files in a different format that are present.
+        let mut siblings = self.sources
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| is_format_sibling(source, other))
+            .filter_map(|(idx, other)| other.meta_path(self.item_path).ok().map(|p| (idx, p, other.format)));
+
+        match preferred {
+            None => match siblings.next() {
+                None => Some(Ok((meta_path, source))),
+                // The conflict was already reported for the earlier meta file.
+                Some((sibling_idx, _, _)) if sibling_idx < source_idx => None,
+                Some((_, sibling_path, _)) => {
+                    Some(Err(SourceError::MultiFormatConflict(meta_path, sibling_path)))
+                },
+            },
+            Some(fmt) if fmt == source.format => Some(Ok((meta_path, source))),
+            // Only skip this meta file if the preferred format is present, so
+            // that the remaining formats are still used if it is not.
+            Some(fmt) => {
+                if siblings.any(|(_, _, sibling_fmt)| sibling_fmt == fmt) {
+                    None
+                } else {
+                    Some(Ok((meta_path, source)))
+                }
+            },
+        }
+    }
+}
+
 impl<'a> Iterator for MetaPaths<'a> {
     type Item = Result<(PathBuf, &'a Source), SourceError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(source) = self.iter.next() {
+        while let Some((source_idx, source)) = self.iter.next() {
             let res = source.meta_path(self.item_path);
 
             match res {
                 Ok(meta_path) => {
-                    return Some(Ok((meta_path, source)));
+                    match self.apply_policy(meta_path, source_idx, source) {
+                        Some(res) => return Some(res),
+                        None => continue,
+                    }
                 }
                 Err(err) if err.is_fatal() => {
                     return Some(Err(err));
@@ -9,21 +9,61 @@ use std::collections::btree_map::{
 };
 use std::iter::{Extend, FromIterator, FusedIterator};
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de::Error as DeError;
+use thiserror::Error;
 
 use crate::types::Value;
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub enum KeyError {
+    #[error("metadata key is empty")]
+    Empty,
+    #[error(r#"metadata key consists only of whitespace: "{0}""#)]
+    Blank(String),
+}
+
 /// Represents a chunk of metadata for one item.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[cfg_attr(test, derive(PartialEq, Eq))]
 #[serde(transparent)]
 pub struct Block(pub(crate) InnerMap<String, Value>);
 
+impl<'de> Deserialize<'de> for Block {
+    /// Deserializes a block, rejecting any keys that fail `Block::check_key`.
+    /// Keys are otherwise kept as-is, and are not trimmed.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = InnerMap::<String, Value>::deserialize(deserializer)?;
+
+        for key in inner.keys() {
+            Self::check_key(key).map_err(D::Error::custom)?;
+        }
+
+        Ok(Self(inner))
+    }
+}
+
 impl Block {
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Checks that a string is usable as a metadata key read from a meta file,
+    /// so that key path lookups are predictable.
+    /// Keys must not be empty, or consist only of whitespace.
+    pub fn check_key(key: &str) -> Result<(), KeyError> {
+        if key.is_empty() {
+            Err(KeyError::Empty)
+        } else if key.trim().is_empty() {
+            Err(KeyError::Blank(key.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn clear(&mut self) {
         self.0.clear()
     }
@@ -276,3 +316,41 @@ impl ExactSizeIterator for IntoIter {
 }
 
 impl FusedIterator for IntoIter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use maplit::btreemap;
+    use str_macro::str;
+
+    use crate::test_util::TestUtil as TU;
+
+    #[test]
+    fn check_key() {
+        assert_eq!(Block::check_key("key"), Ok(()));
+        assert_eq!(Block::check_key(" key "), Ok(()));
+        assert_eq!(Block::check_key(""), Err(KeyError::Empty));
+        assert_eq!(Block::check_key(" \t"), Err(KeyError::Blank(str!(" \t"))));
+    }
+
+    #[test]
+    fn deserialize() {
+        let expected = Block(btreemap![
+            str!("key") => TU::s("val"),
+            str!(" padded ") => TU::s("val"),
+        ]);
+        let produced: Block = serde_yaml::from_str("{key: val, ' padded ': val}").unwrap();
+        assert_eq!(expected, produced);
+
+        let produced = serde_json::from_str::<Block>(r#"{"key": "val", "": "val"}"#).unwrap_err();
+        assert!(produced.to_string().contains("metadata key is empty"));
+
+        let produced = serde_yaml::from_str::<Block>("{key: val, '  ': val}").unwrap_err();
+        assert!(produced.to_string().contains("metadata key consists only of whitespace"));
+
+        // Nested blocks are checked as well.
+        assert!(serde_json::from_str::<Block>(r#"{"key": {"": "val"}}"#).is_err());
+        assert!(serde_json::from_str::<Block>(r#"{"key": [{"ok": 1}, {"": 2}]}"#).is_err());
+    }
+}
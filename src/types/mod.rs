@@ -4,8 +4,8 @@ pub mod block_map;
 mod number;
 mod value;
 
-pub use self::block::Block;
+pub use self::block::{Block, KeyError};
 pub use self::block_seq::BlockSeq;
 pub use self::block_map::BlockMap;
-pub use self::number::Number;
-pub use self::value::{Value, Sequence, Decimal, Error as ValueError};
+pub use self::number::{Number, Error as NumberError};
+pub use self::value::{Value, ValueKind, Sequence, Decimal, Error as ValueError};
@@ -1,7 +1,18 @@
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::ops::{Add, Sub, Mul, Div, Rem, Neg};
+use std::str::FromStr;
 
 use rust_decimal::Decimal;
+use thiserror::Error;
+
+#[derive(Debug, Error, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Error {
+    #[error("cannot divide by zero")]
+    DivideByZero,
+    #[error("numeric overflow")]
+    Overflow,
+}
 
 /// Wrapper type to smooth over the differences between integers and decimals.
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
@@ -43,6 +54,83 @@ impl Number {
             Ordering::Equal | Ordering::Less => self,
         }
     }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            Self::Integer(i) => *i == 0,
+            Self::Decimal(d) => d.is_zero(),
+        }
+    }
+
+    fn to_decimal(self) -> Decimal {
+        match self {
+            Self::Integer(i) => Decimal::from(i),
+            Self::Decimal(d) => d,
+        }
+    }
+
+    /// Divides this number by another, without panicking.
+    /// Integer division stays integer if it is exact, and is promoted to
+    /// decimal division otherwise.
+    pub fn checked_div(self, other: Self) -> Result<Self, Error> {
+        if other.is_zero() {
+            return Err(Error::DivideByZero);
+        }
+
+        if let (Self::Integer(l), Self::Integer(r)) = (self, other) {
+            if l.checked_rem(r) == Some(0) {
+                if let Some(q) = l.checked_div(r) {
+                    return Ok(Self::Integer(q));
+                }
+            }
+        }
+
+        self.to_decimal()
+            .checked_div(other.to_decimal())
+            .map(Self::Decimal)
+            .ok_or(Error::Overflow)
+    }
+
+    /// Raises this number to an integer power, without panicking.
+    /// Integer bases with non-negative exponents stay integer, unless the
+    /// result overflows, in which case they are promoted to decimal. Negative
+    /// exponents always produce decimals.
+    pub fn checked_pow(self, exp: i64) -> Result<Self, Error> {
+        if exp < 0 && self.is_zero() {
+            return Err(Error::DivideByZero);
+        }
+
+        if let Self::Integer(base) = self {
+            let int_pow = u32::try_from(exp).ok().and_then(|e| base.checked_pow(e));
+
+            if let Some(p) = int_pow {
+                return Ok(Self::Integer(p));
+            }
+        }
+
+        // Exponentiation by squaring.
+        let mut base = self.to_decimal();
+        let mut rem_exp = exp.unsigned_abs();
+        let mut acc = Decimal::ONE;
+
+        while rem_exp > 0 {
+            if rem_exp & 1 == 1 {
+                acc = acc.checked_mul(base).ok_or(Error::Overflow)?;
+            }
+
+            rem_exp >>= 1;
+
+            if rem_exp > 0 {
+                base = base.checked_mul(base).ok_or(Error::Overflow)?;
+            }
+        }
+
+        if exp < 0 {
+            acc = Decimal::ONE.checked_div(acc).ok_or(Error::Overflow)?;
+        }
+
+        Ok(Self::Decimal(acc))
+    }
 }
 
 impl From<i64> for Number {
@@ -69,6 +157,19 @@ impl From<&Decimal> for Number {
     }
 }
 
+impl FromStr for Number {
+    type Err = rust_decimal::Error;
+
+    /// Parses a number, as an integer if possible, and as a decimal otherwise.
+    /// Decimals are parsed exactly, and keep their written precision.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match i64::from_str(s) {
+            Ok(i) => Ok(Self::Integer(i)),
+            Err(_) => Decimal::from_str(s).map(Self::Decimal),
+        }
+    }
+}
+
 impl Add for Number {
     type Output = Number;
 
@@ -309,6 +410,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn from_str() {
+        assert_eq!(Number::from_str("12"), Ok(Number::Integer(12)));
+        assert_eq!(Number::from_str("-12"), Ok(Number::Integer(-12)));
+        assert_eq!(Number::from_str("1.5"), Ok(Number::Decimal(dec!(1.5))));
+        assert_eq!(Number::from_str("1.50"), Ok(Number::Decimal(dec!(1.50))));
+        assert_eq!(Number::from_str("12.0"), Ok(Number::Decimal(dec!(12.0))));
+
+        // Integers that do not fit in an `i64` are parsed as decimals.
+        assert_eq!(Number::from_str("9223372036854775808"), Ok(Number::Decimal(dec!(9223372036854775808))));
+
+        assert!(Number::from_str("abc").is_err());
+        assert!(Number::from_str("").is_err());
+    }
+
     #[test]
     fn add() {
         for l in -3..=3 {
@@ -492,6 +608,53 @@ mod tests {
         assert_eq!(expected_b_neg_a_neg, input_b_neg.div(input_a_neg));
     }
 
+    #[test]
+    fn checked_div() {
+        // Exact integer division stays integer.
+        assert_eq!(Number::Integer(6).checked_div(Number::Integer(3)), Ok(Number::Integer(2)));
+        assert_eq!(Number::Integer(-6).checked_div(Number::Integer(3)), Ok(Number::Integer(-2)));
+
+        // Inexact integer division is promoted to decimal.
+        assert_eq!(Number::Integer(7).checked_div(Number::Integer(2)), Ok(Number::Decimal(dec!(3.5))));
+        assert_eq!(Number::Integer(-1).checked_div(Number::Integer(4)), Ok(Number::Decimal(dec!(-0.25))));
+        assert_eq!(Number::Integer(i64::MIN).checked_div(Number::Integer(-1)), Ok(Number::Decimal(dec!(9223372036854775808))));
+
+        // Any decimal operand produces a decimal.
+        assert_eq!(Number::Integer(6).checked_div(Number::Decimal(dec!(3))), Ok(Number::Decimal(dec!(2))));
+        assert_eq!(Number::Decimal(dec!(7.5)).checked_div(Number::Integer(3)), Ok(Number::Decimal(dec!(2.5))));
+
+        // Zero divisors are an error, not a panic.
+        assert_eq!(Number::Integer(1).checked_div(Number::Integer(0)), Err(Error::DivideByZero));
+        assert_eq!(Number::Integer(1).checked_div(Number::Decimal(dec!(0.0))), Err(Error::DivideByZero));
+        assert_eq!(Number::Decimal(dec!(1.5)).checked_div(Number::Integer(0)), Err(Error::DivideByZero));
+
+        assert_eq!(Number::Decimal(Decimal::MAX).checked_div(Number::Decimal(dec!(0.1))), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn checked_pow() {
+        assert_eq!(Number::Integer(2).checked_pow(10), Ok(Number::Integer(1024)));
+        assert_eq!(Number::Integer(-3).checked_pow(3), Ok(Number::Integer(-27)));
+        assert_eq!(Number::Integer(5).checked_pow(0), Ok(Number::Integer(1)));
+        assert_eq!(Number::Integer(0).checked_pow(0), Ok(Number::Integer(1)));
+
+        // Decimal bases produce decimals.
+        assert_eq!(Number::Decimal(dec!(1.5)).checked_pow(2), Ok(Number::Decimal(dec!(2.25))));
+        assert_eq!(Number::Decimal(dec!(-0.1)).checked_pow(3), Ok(Number::Decimal(dec!(-0.001))));
+        assert_eq!(Number::Decimal(dec!(2.5)).checked_pow(0), Ok(Number::Decimal(dec!(1))));
+
+        // Negative exponents produce decimals.
+        assert_eq!(Number::Integer(2).checked_pow(-2), Ok(Number::Decimal(dec!(0.25))));
+        assert_eq!(Number::Integer(-2).checked_pow(-3), Ok(Number::Decimal(dec!(-0.125))));
+        assert_eq!(Number::Decimal(dec!(0.5)).checked_pow(-1), Ok(Number::Decimal(dec!(2))));
+        assert_eq!(Number::Integer(0).checked_pow(-1), Err(Error::DivideByZero));
+        assert_eq!(Number::Decimal(dec!(0.0)).checked_pow(-2), Err(Error::DivideByZero));
+
+        // Integer overflow is promoted to decimal, until that overflows too.
+        assert_eq!(Number::Integer(10).checked_pow(19), Ok(Number::Decimal(dec!(10000000000000000000))));
+        assert_eq!(Number::Integer(10).checked_pow(40), Err(Error::Overflow));
+    }
+
     #[test]
     fn rem() {
         for l in -3..=3 {
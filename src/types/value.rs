@@ -1,20 +1,29 @@
 //! Primitive metadata value types.
 
 use std::convert::TryFrom;
+use std::str::FromStr;
 
 pub use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
 
 use serde::Deserialize;
 use serde::Serialize;
 use strum::{EnumDiscriminants, AsRefStr};
 use thiserror::Error;
 
+use crate::config::format::ExactJson;
 use crate::types::{Block, Number};
 
 #[derive(Debug, Error, Copy, Clone, PartialEq, Hash)]
 pub enum Error {
     #[error("cannot convert value of kind {} into target type", .0.as_ref())]
     CannotConvert(ValueKind),
+    #[error("expected value of kind {}, found kind {}", .expected.as_ref(), .found.as_ref())]
+    WrongType { expected: ValueKind, found: ValueKind },
+    #[error("cannot coerce value of kind {} into kind {}", .0.as_ref(), .1.as_ref())]
+    CannotCoerce(ValueKind, ValueKind),
+    #[error("value of kind {} is not numeric", .0.as_ref())]
+    NotNumeric(ValueKind),
 }
 
 // Re-exporting to allow downstream users to ensure usage of the correct types.
@@ -22,6 +31,45 @@ pub type Integer = i64;
 pub type Boolean = bool;
 pub type Sequence = Vec<Value>;
 
+/// Parses a key as an index into a sequence, if it consists only of decimal digits.
+fn parse_index(key: &str) -> Option<usize> {
+    if key.is_empty() || !key.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    key.parse().ok()
+}
+
+/// Splits a string key path on a separator, handling backslash escapes.
+/// Returns `None` on an empty segment or a dangling backslash.
+fn split_str_path(path: &str, sep: char) -> Option<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut curr_key = String::new();
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            curr_key.push(chars.next()?);
+        } else if c == sep {
+            if curr_key.is_empty() {
+                return None;
+            }
+
+            keys.push(std::mem::take(&mut curr_key));
+        } else {
+            curr_key.push(c);
+        }
+    }
+
+    if curr_key.is_empty() {
+        return None;
+    }
+
+    keys.push(curr_key);
+
+    Some(keys)
+}
+
 /// Represents the types of data that can be used as metadata values.
 #[derive(Debug, Clone, Deserialize, Serialize, EnumDiscriminants)]
 #[cfg_attr(test, derive(PartialEq, Eq))]
@@ -39,27 +87,238 @@ pub enum Value {
 
 impl Value {
     /// Given a list of keys, looks up the subvalue at that key path of this value.
-    /// This only works if this value is a mapping.
+    /// Mappings are indexed by key, and sequences are indexed by keys that are
+    /// non-negative integers written in decimal digits, e.g. `"0"`. An index
+    /// that is out of range returns `None`.
     pub fn get_key_path<S: AsRef<str>>(&self, key_path: &[S]) -> Option<&Self> {
         let mut curr_val = self;
 
         for key in key_path {
-            // See if the current meta value is indeed a mapping.
-            match curr_val {
-                Self::Mapping(map) => {
-                    // See if the current key in the key path is found in this mapping.
-                    // If it is, set it as the new current value.
-                    curr_val = map.get(key.as_ref())?;
-                },
-
-                // An attempt was made to get the key of a non-mapping, short circuit.
+            let key = key.as_ref();
+
+            curr_val = match curr_val {
+                // See if the current key in the key path is found in this mapping.
+                // If it is, set it as the new current value.
+                Self::Mapping(map) => map.get(key)?,
+
+                // See if the current key is an index into this sequence.
+                Self::Sequence(seq) => seq.get(parse_index(key)?)?,
+
+                // An attempt was made to get the key of a scalar, short circuit.
                 _ => return None,
-            }
+            };
         }
 
         // The remaining current value is what is needed to return.
         Some(curr_val)
     }
+
+    /// Similar to `get_key_path`, but takes a single string of keys joined
+    /// by a separator character, e.g. `"key_a.key_b"` with a separator of `.`.
+    /// A backslash escapes the following character, so that keys can contain
+    /// the separator or a backslash themselves (e.g. `"key\\.a"` is the single
+    /// key `"key.a"`).
+    /// Returns `None` if the path has an empty segment, including if the path
+    /// itself is empty, or ends with a lone backslash.
+    pub fn get_str_path(&self, path: &str, sep: char) -> Option<&Self> {
+        let key_path = split_str_path(path, sep)?;
+        self.get_key_path(&key_path)
+    }
+
+    /// Recursively removes mapping entries for which a predicate returns false.
+    /// The predicate is called with each key and its value before that value's
+    /// own entries are pruned. This recurses into sequences as well, so any
+    /// mappings nested inside sequences are pruned too.
+    pub fn retain_keys<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &Value) -> bool,
+    {
+        self.retain_keys_with(&mut f)
+    }
+
+    fn retain_keys_with<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(&str, &Value) -> bool,
+    {
+        match self {
+            Self::Mapping(map) => {
+                map.0.retain(|k, v| {
+                    let keep = f(k, v);
+
+                    if keep {
+                        v.retain_keys_with(f);
+                    }
+
+                    keep
+                });
+            },
+            Self::Sequence(seq) => {
+                for v in seq.iter_mut() {
+                    v.retain_keys_with(f);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Deep-merges another value into this one, with the other value taking
+    /// precedence. If both values are mappings, their entries are merged
+    /// recursively, key by key. Otherwise, the other value replaces this one;
+    /// notably, sequences are replaced wholesale instead of being concatenated,
+    /// so that an override can also remove elements.
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Mapping(mut base), Self::Mapping(over)) => {
+                for (key, over_val) in over {
+                    let merged = match base.remove(&key) {
+                        Some(base_val) => base_val.merge(over_val),
+                        None => over_val,
+                    };
+
+                    base.insert(key, merged);
+                }
+
+                Self::Mapping(base)
+            },
+            (_, other) => other,
+        }
+    }
+
+    /// Renders this value into a human-readable string, with nested sequences
+    /// and mappings indented by a given number of spaces per level.
+    /// Mapping keys are always in sorted order, so the output is stable and
+    /// suitable for logging and diffs, but it is not meant to be parsed back.
+    /// Decimals are written without trailing zeros, but always with a decimal
+    /// point, so that they can be told apart from integers.
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        let mut out = String::new();
+        self.write_pretty(indent, 0, &mut out);
+        out
+    }
+
+    fn write_pretty(&self, indent: usize, level: usize, out: &mut String) {
+        let pad = |level: usize, out: &mut String| out.push_str(&" ".repeat(indent * level));
+
+        match self {
+            Self::Null => out.push_str("null"),
+            Self::String(s) => out.push_str(&format!("{:?}", s)),
+            Self::Integer(i) => out.push_str(&i.to_string()),
+            Self::Boolean(b) => out.push_str(&b.to_string()),
+            Self::Decimal(d) => {
+                let d = d.normalize();
+                out.push_str(&d.to_string());
+
+                if d.scale() == 0 {
+                    out.push_str(".0");
+                }
+            },
+            Self::Sequence(seq) if seq.is_empty() => out.push_str("[]"),
+            Self::Sequence(seq) => {
+                out.push_str("[\n");
+
+                for (i, val) in seq.iter().enumerate() {
+                    pad(level + 1, out);
+                    val.write_pretty(indent, level + 1, out);
+                    out.push_str(if i + 1 < seq.len() { ",\n" } else { "\n" });
+                }
+
+                pad(level, out);
+                out.push(']');
+            },
+            Self::Mapping(block) if block.is_empty() => out.push_str("{}"),
+            Self::Mapping(block) => {
+                out.push_str("{\n");
+
+                for (i, (key, val)) in block.iter().enumerate() {
+                    pad(level + 1, out);
+                    out.push_str(&format!("{:?}: ", key));
+                    val.write_pretty(indent, level + 1, out);
+                    out.push_str(if i + 1 < block.len() { ",\n" } else { "\n" });
+                }
+
+                pad(level, out);
+                out.push('}');
+            },
+        }
+    }
+
+    /// Attempts to coerce this value into a value of a target kind.
+    /// Strings are parsed into numbers and booleans, scalars are rendered into
+    /// strings, and any non-sequence value can be wrapped into a one-element
+    /// sequence. Decimals coerced into integers are truncated towards zero.
+    /// Coercing into the value's own kind always succeeds and is a no-op.
+    pub fn coerce_to(self, target: ValueKind) -> Result<Self, Error> {
+        let source = ValueKind::from(&self);
+
+        if source == target {
+            return Ok(self);
+        }
+
+        let fail = || Error::CannotCoerce(source, target);
+
+        match (self, target) {
+            (Self::String(s), ValueKind::Integer) => {
+                Integer::from_str(s.trim()).map(Self::Integer).map_err(|_| fail())
+            },
+            (Self::String(s), ValueKind::Decimal) => {
+                Decimal::from_str(s.trim()).map(Self::Decimal).map_err(|_| fail())
+            },
+            (Self::String(s), ValueKind::Boolean) => {
+                Boolean::from_str(s.trim()).map(Self::Boolean).map_err(|_| fail())
+            },
+            (Self::Integer(i), ValueKind::String) => Ok(Self::String(i.to_string())),
+            (Self::Decimal(d), ValueKind::String) => Ok(Self::String(d.to_string())),
+            (Self::Boolean(b), ValueKind::String) => Ok(Self::String(b.to_string())),
+            (Self::Integer(i), ValueKind::Decimal) => Ok(Self::Decimal(Decimal::from(i))),
+            (Self::Decimal(d), ValueKind::Integer) => {
+                d.trunc().to_i64().map(Self::Integer).ok_or_else(fail)
+            },
+            (value, ValueKind::Sequence) => Ok(Self::Sequence(vec![value])),
+            _ => Err(fail()),
+        }
+    }
+
+    /// Converts this value into a string value for display.
+    /// Scalars are rendered into their canonical strings, with null as `null`,
+    /// and strings are left unchanged. Decimals are written as-is, with a `.`
+    /// as the decimal point and no digit grouping. Sequences and mappings are
+    /// rendered as compact JSON if `compact_containers` is set, with decimals
+    /// written exactly, and are an error otherwise.
+    pub fn stringify(self, compact_containers: bool) -> Result<Self, Error> {
+        let source = ValueKind::from(&self);
+
+        let s = match self {
+            Self::Null => String::from("null"),
+            Self::String(s) => s,
+            Self::Integer(i) => i.to_string(),
+            Self::Boolean(b) => b.to_string(),
+            Self::Decimal(d) => d.to_string(),
+            Self::Sequence(..) | Self::Mapping(..) => {
+                if !compact_containers {
+                    return Err(Error::CannotCoerce(source, ValueKind::String));
+                }
+
+                ExactJson(&self).to_string()
+            },
+        };
+
+        Ok(Self::String(s))
+    }
+
+    /// Converts this value into a number value, parsing strings into integers
+    /// if possible, and into decimals otherwise. Integers and decimals are
+    /// passed through as-is.
+    pub fn parse_number(self) -> Result<Self, Error> {
+        match self {
+            Self::Integer(..) | Self::Decimal(..) => Ok(self),
+            Self::String(s) => {
+                Number::from_str(s.trim())
+                    .map(Self::from)
+                    .map_err(|_| Error::NotNumeric(ValueKind::String))
+            },
+            _ => Err(Error::NotNumeric(ValueKind::from(&self))),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -81,7 +340,7 @@ impl TryFrom<Value> for String {
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
             Value::String(s) => Ok(s),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::String, found: value.into() }),
         }
     }
 }
@@ -92,7 +351,7 @@ impl<'k> TryFrom<&'k Value> for &'k str {
     fn try_from(value: &'k Value) -> Result<Self, Self::Error> {
         match value {
             &Value::String(ref s) => Ok(s),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::String, found: value.into() }),
         }
     }
 }
@@ -115,7 +374,7 @@ impl TryFrom<Value> for Integer {
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
             Value::Integer(i) => Ok(i),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Integer, found: value.into() }),
         }
     }
 }
@@ -126,7 +385,7 @@ impl<'k> TryFrom<&'k Value> for Integer {
     fn try_from(value: &'k Value) -> Result<Self, Self::Error> {
         match value {
             &Value::Integer(i) => Ok(i),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Integer, found: value.into() }),
         }
     }
 }
@@ -149,7 +408,7 @@ impl TryFrom<Value> for Boolean {
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
             Value::Boolean(b) => Ok(b),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Boolean, found: value.into() }),
         }
     }
 }
@@ -160,7 +419,7 @@ impl<'k> TryFrom<&'k Value> for Boolean {
     fn try_from(value: &'k Value) -> Result<Self, Self::Error> {
         match value {
             &Value::Boolean(b) => Ok(b),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Boolean, found: value.into() }),
         }
     }
 }
@@ -183,7 +442,7 @@ impl TryFrom<Value> for Decimal {
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
             Value::Decimal(d) => Ok(d),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Decimal, found: value.into() }),
         }
     }
 }
@@ -194,7 +453,7 @@ impl<'k> TryFrom<&'k Value> for Decimal {
     fn try_from(value: &'k Value) -> Result<Self, Self::Error> {
         match value {
             &Value::Decimal(d) => Ok(d),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Decimal, found: value.into() }),
         }
     }
 }
@@ -211,7 +470,18 @@ impl TryFrom<Value> for Sequence {
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
             Value::Sequence(s) => Ok(s),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Sequence, found: value.into() }),
+        }
+    }
+}
+
+impl<'k> TryFrom<&'k Value> for &'k Sequence {
+    type Error = Error;
+
+    fn try_from(value: &'k Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Sequence(s) => Ok(s),
+            _ => Err(Error::WrongType { expected: ValueKind::Sequence, found: value.into() }),
         }
     }
 }
@@ -228,7 +498,18 @@ impl TryFrom<Value> for Block {
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
             Value::Mapping(m) => Ok(m),
-            _ => Err(Error::CannotConvert(value.into())),
+            _ => Err(Error::WrongType { expected: ValueKind::Mapping, found: value.into() }),
+        }
+    }
+}
+
+impl<'k> TryFrom<&'k Value> for &'k Block {
+    type Error = Error;
+
+    fn try_from(value: &'k Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Mapping(m) => Ok(m),
+            _ => Err(Error::WrongType { expected: ValueKind::Mapping, found: value.into() }),
         }
     }
 }
@@ -280,6 +561,8 @@ mod tests {
     use rust_decimal_macros::dec;
     use str_macro::str;
 
+    use crate::test_util::TestUtil as TU;
+
     #[test]
     fn deserialize() {
         let inputs_and_expected = vec![
@@ -368,6 +651,170 @@ mod tests {
         }
     }
 
+    #[test]
+    fn retain_keys() {
+        let mut value = Value::Mapping(Block(btreemap![
+            str!("title") => TU::s("title"),
+            str!("_internal") => TU::s("hidden"),
+            str!("nested") => Value::Mapping(Block(btreemap![
+                str!("_id") => Value::Integer(1),
+                str!("name") => TU::s("name"),
+            ])),
+            str!("_nested") => Value::Mapping(Block(btreemap![
+                str!("name") => TU::s("name"),
+            ])),
+            str!("tracks") => Value::Sequence(vec![
+                Value::Mapping(Block(btreemap![
+                    str!("_id") => Value::Integer(2),
+                    str!("title") => TU::s("track"),
+                ])),
+                TU::s("_not_a_key"),
+            ]),
+        ]));
+
+        let expected = Value::Mapping(Block(btreemap![
+            str!("title") => TU::s("title"),
+            str!("nested") => Value::Mapping(Block(btreemap![
+                str!("name") => TU::s("name"),
+            ])),
+            str!("tracks") => Value::Sequence(vec![
+                Value::Mapping(Block(btreemap![
+                    str!("title") => TU::s("track"),
+                ])),
+                TU::s("_not_a_key"),
+            ]),
+        ]));
+
+        let mut visited = 0;
+        value.retain_keys(|k, _| {
+            visited += 1;
+            !k.starts_with('_')
+        });
+        assert_eq!(expected, value);
+
+        // Children of removed entries are never visited.
+        assert_eq!(9, visited);
+
+        // Non-mapping values are unaffected.
+        let mut value = TU::sample_flat_sequence();
+        value.retain_keys(|_, _| false);
+        assert_eq!(TU::sample_flat_sequence(), value);
+    }
+
+    #[test]
+    fn coerce_to() {
+        let inputs_and_expected = vec![
+            // No-op coercions.
+            ((Value::Null, ValueKind::Null), Ok(Value::Null)),
+            ((TU::s("a"), ValueKind::String), Ok(TU::s("a"))),
+
+            // Strings into numbers and booleans.
+            ((TU::s("27"), ValueKind::Integer), Ok(Value::Integer(27))),
+            ((TU::s(" -27 "), ValueKind::Integer), Ok(Value::Integer(-27))),
+            ((TU::s("3.1415"), ValueKind::Decimal), Ok(Value::Decimal(dec!(3.1415)))),
+            ((TU::s("27"), ValueKind::Decimal), Ok(Value::Decimal(dec!(27)))),
+            ((TU::s("true"), ValueKind::Boolean), Ok(Value::Boolean(true))),
+            ((TU::s("3.1415"), ValueKind::Integer), Err(Error::CannotCoerce(ValueKind::String, ValueKind::Integer))),
+            ((TU::s("abc"), ValueKind::Decimal), Err(Error::CannotCoerce(ValueKind::String, ValueKind::Decimal))),
+            ((TU::s("yes"), ValueKind::Boolean), Err(Error::CannotCoerce(ValueKind::String, ValueKind::Boolean))),
+
+            // Scalars into strings.
+            ((Value::Integer(27), ValueKind::String), Ok(TU::s("27"))),
+            ((Value::Decimal(dec!(3.1415)), ValueKind::String), Ok(TU::s("3.1415"))),
+            ((Value::Boolean(false), ValueKind::String), Ok(TU::s("false"))),
+            ((Value::Null, ValueKind::String), Err(Error::CannotCoerce(ValueKind::Null, ValueKind::String))),
+
+            // Between number types.
+            ((Value::Integer(27), ValueKind::Decimal), Ok(Value::Decimal(dec!(27)))),
+            ((Value::Decimal(dec!(3.9)), ValueKind::Integer), Ok(Value::Integer(3))),
+            ((Value::Decimal(dec!(-3.9)), ValueKind::Integer), Ok(Value::Integer(-3))),
+            ((Value::Boolean(true), ValueKind::Integer), Err(Error::CannotCoerce(ValueKind::Boolean, ValueKind::Integer))),
+
+            // Wrapping into sequences.
+            ((Value::Integer(27), ValueKind::Sequence), Ok(Value::Sequence(vec![Value::Integer(27)]))),
+            ((Value::Null, ValueKind::Sequence), Ok(Value::Sequence(vec![Value::Null]))),
+            ((TU::sample_flat_sequence(), ValueKind::Sequence), Ok(TU::sample_flat_sequence())),
+            ((TU::sample_flat_mapping(), ValueKind::Sequence), Ok(Value::Sequence(vec![TU::sample_flat_mapping()]))),
+
+            // Sequences and mappings do not coerce into scalars.
+            ((TU::sample_flat_sequence(), ValueKind::String), Err(Error::CannotCoerce(ValueKind::Sequence, ValueKind::String))),
+            ((TU::sample_flat_mapping(), ValueKind::Integer), Err(Error::CannotCoerce(ValueKind::Mapping, ValueKind::Integer))),
+            ((Value::Integer(27), ValueKind::Mapping), Err(Error::CannotCoerce(ValueKind::Integer, ValueKind::Mapping))),
+        ];
+
+        for (input, expected) in inputs_and_expected {
+            let (value, target) = input;
+            let produced = value.coerce_to(target);
+            assert_eq!(expected, produced);
+        }
+    }
+
+    #[test]
+    fn stringify() {
+        let inputs_and_expected = vec![
+            (Value::Null, Ok(TU::s("null"))),
+            (TU::s("string"), Ok(TU::s("string"))),
+            (Value::Integer(-27), Ok(TU::s("-27"))),
+            (Value::Boolean(false), Ok(TU::s("false"))),
+            (Value::Decimal(dec!(1234567.50)), Ok(TU::s("1234567.50"))),
+            (Value::Decimal(dec!(-0.001)), Ok(TU::s("-0.001"))),
+        ];
+
+        for compact_containers in [false, true] {
+            for (input, expected) in inputs_and_expected.clone() {
+                let produced = input.stringify(compact_containers);
+                assert_eq!(expected, produced);
+            }
+        }
+
+        // Containers are an error, unless compact rendering is enabled.
+        let seq = Value::Sequence(vec![TU::s("a"), Value::Integer(1), Value::Null]);
+        let map = Value::Mapping(Block(btreemap![
+            str!("b") => Value::Boolean(true),
+            str!("a") => Value::Sequence(vec![]),
+        ]));
+
+        assert_eq!(seq.clone().stringify(false), Err(Error::CannotCoerce(ValueKind::Sequence, ValueKind::String)));
+        assert_eq!(map.clone().stringify(false), Err(Error::CannotCoerce(ValueKind::Mapping, ValueKind::String)));
+        assert_eq!(seq.stringify(true), Ok(TU::s(r#"["a",1,null]"#)));
+        assert_eq!(map.stringify(true), Ok(TU::s(r#"{"a":[],"b":true}"#)));
+
+        // Decimals in containers are written exactly.
+        let seq = Value::Sequence(vec![
+            Value::Decimal(dec!(1.10)),
+            Value::Decimal(dec!(0.1000000000000000055511151231)),
+        ]);
+        assert_eq!(seq.stringify(true), Ok(TU::s("[1.10,0.1000000000000000055511151231]")));
+    }
+
+    #[test]
+    fn parse_number() {
+        let inputs_and_expected = vec![
+            (TU::s("12"), Ok(Value::Integer(12))),
+            (TU::s(" -12 "), Ok(Value::Integer(-12))),
+            (TU::s("1.5"), Ok(Value::Decimal(dec!(1.5)))),
+            (TU::s("0.10"), Ok(Value::Decimal(dec!(0.10)))),
+            (Value::Integer(27), Ok(Value::Integer(27))),
+            (Value::Decimal(dec!(3.1415)), Ok(Value::Decimal(dec!(3.1415)))),
+            (TU::s("abc"), Err(Error::NotNumeric(ValueKind::String))),
+            (TU::s(""), Err(Error::NotNumeric(ValueKind::String))),
+            (Value::Boolean(true), Err(Error::NotNumeric(ValueKind::Boolean))),
+            (Value::Null, Err(Error::NotNumeric(ValueKind::Null))),
+            (TU::sample_flat_sequence(), Err(Error::NotNumeric(ValueKind::Sequence))),
+        ];
+
+        for (input, expected) in inputs_and_expected {
+            let produced = input.parse_number();
+            assert_eq!(expected, produced);
+        }
+
+        // Decimals keep their written precision.
+        match TU::s("1.50").parse_number() {
+            Ok(Value::Decimal(d)) => assert_eq!(d.scale(), 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
     #[test]
     fn get_key_path() {
         let key_str_a = "key_a";
@@ -417,10 +864,18 @@ mod tests {
             ((&val_seq_a, vec![]), Some(&val_seq_a)),
             ((&val_map_a, vec![]), Some(&val_map_a)),
 
-            // A non-empty key path returns no value on non-maps.
+            // A non-empty key path returns no value on scalars.
             ((&val_nil, vec![key_str_a]), None),
             ((&val_str_a, vec![key_str_a]), None),
+            ((&val_str_a, vec!["0"]), None),
+
+            // Sequences are only indexed by in-range numeric keys.
             ((&val_seq_a, vec![key_str_a]), None),
+            ((&val_seq_a, vec!["3"]), None),
+            ((&val_seq_a, vec!["-1"]), None),
+            ((&val_seq_a, vec!["+1"]), None),
+            ((&val_seq_a, vec![""]), None),
+            ((&val_seq_a, vec!["99999999999999999999999"]), None),
 
             // If the key is not found in a mapping, nothing is returned.
             ((&val_map_a, vec![key_str_x]), None),
@@ -445,6 +900,14 @@ mod tests {
             ((&val_map_d, vec![key_str_b, key_str_b]), Some(&val_seq_b)),
             ((&val_map_d, vec![key_str_c, key_str_c]), Some(&val_nil)),
 
+            // Sequence indexing, including nested in mappings.
+            ((&val_seq_a, vec!["0"]), Some(&val_str_a)),
+            ((&val_seq_a, vec!["2"]), Some(&val_str_a)),
+            ((&val_map_b, vec![key_str_b, "1"]), Some(&val_str_b)),
+            ((&val_map_d, vec![key_str_b, key_str_c, "0"]), Some(&val_str_c)),
+            ((&val_map_d, vec![key_str_b, key_str_c, "3"]), None),
+            ((&val_map_d, vec![key_str_b, key_str_c, "0", "0"]), None),
+
         ];
 
         for (input, expected) in inputs_and_expected {
@@ -452,5 +915,198 @@ mod tests {
             let produced = val.get_key_path(&key_path);
             assert_eq!(expected, produced);
         }
+
+        // String key paths.
+        assert_eq!(val_map_d.get_str_path("key_a.key_a", '.'), Some(&val_str_a));
+        assert_eq!(val_map_d.get_str_path("key_b/key_b", '/'), Some(&val_seq_b));
+        assert_eq!(val_map_d.get_str_path("key_c", '.'), Some(&val_map_c));
+        assert_eq!(val_map_d.get_str_path("key_a.key_x", '.'), None);
+        assert_eq!(val_map_d.get_str_path("key_a.key_a", '/'), None);
+
+        let val_seq_d = Value::from(vec![val_map_a.clone(), val_map_b.clone()]);
+        let val_map_f = Value::from(Block(btreemap![str!("items") => val_seq_d]));
+        assert_eq!(val_map_f.get_str_path("items.0.key_b", '.'), Some(&val_str_b));
+        assert_eq!(val_map_f.get_str_path("items.1.key_c.2", '.'), Some(&val_str_c));
+        assert_eq!(val_map_f.get_str_path("items.2.key_b", '.'), None);
+
+        // Empty segments and dangling escapes are rejected.
+        assert_eq!(val_map_d.get_str_path("", '.'), None);
+        assert_eq!(val_map_d.get_str_path("key_a.", '.'), None);
+        assert_eq!(val_map_d.get_str_path(".key_a", '.'), None);
+        assert_eq!(val_map_d.get_str_path("key_a..key_a", '.'), None);
+        assert_eq!(val_map_d.get_str_path("key_a\\", '.'), None);
+
+        // Escaped separators and backslashes are part of the key.
+        let val_map_e = Value::from(Block(btreemap![
+            str!("key.a") => val_map_a.clone(),
+            str!("key\\b") => val_str_b.clone(),
+        ]));
+        assert_eq!(val_map_e.get_str_path("key\\.a.key_a", '.'), Some(&val_str_a));
+        assert_eq!(val_map_e.get_str_path("key\\\\b", '.'), Some(&val_str_b));
+        assert_eq!(val_map_e.get_str_path("key.a", '.'), None);
+    }
+
+    #[test]
+    fn try_from() {
+        let wrong_type = |expected, found| Error::WrongType { expected, found };
+
+        // Owned conversions.
+        assert_eq!(String::try_from(TU::s("a")), Ok(str!("a")));
+        assert_eq!(Integer::try_from(TU::i(1)), Ok(1));
+        assert_eq!(Boolean::try_from(Value::Boolean(true)), Ok(true));
+        assert_eq!(Sequence::try_from(Value::Sequence(vec![TU::i(1)])), Ok(vec![TU::i(1)]));
+        assert_eq!(Block::try_from(Value::Mapping(Block::new())), Ok(Block::new()));
+
+        assert_eq!(String::try_from(TU::i(1)), Err(wrong_type(ValueKind::String, ValueKind::Integer)));
+        assert_eq!(Integer::try_from(TU::s("1")), Err(wrong_type(ValueKind::Integer, ValueKind::String)));
+        assert_eq!(Sequence::try_from(Value::Null), Err(wrong_type(ValueKind::Sequence, ValueKind::Null)));
+        assert_eq!(Block::try_from(TU::i(1)), Err(wrong_type(ValueKind::Mapping, ValueKind::Integer)));
+
+        // Integers do not silently coerce to booleans, or vice versa.
+        assert_eq!(Boolean::try_from(TU::i(1)), Err(wrong_type(ValueKind::Boolean, ValueKind::Integer)));
+        assert_eq!(Boolean::try_from(&TU::i(0)), Err(wrong_type(ValueKind::Boolean, ValueKind::Integer)));
+        assert_eq!(Integer::try_from(Value::Boolean(true)), Err(wrong_type(ValueKind::Integer, ValueKind::Boolean)));
+
+        // Borrowed conversions.
+        let seq = Value::Sequence(vec![TU::i(1), TU::i(2)]);
+        let map = Value::Mapping(TU::core_flat_mapping());
+
+        assert_eq!(<&str>::try_from(&TU::s("a")), Ok("a"));
+        assert_eq!(Integer::try_from(&TU::i(1)), Ok(1));
+        assert_eq!(Boolean::try_from(&Value::Boolean(false)), Ok(false));
+        assert_eq!(<&Sequence>::try_from(&seq), Ok(&vec![TU::i(1), TU::i(2)]));
+        assert_eq!(<&Block>::try_from(&map), Ok(&TU::core_flat_mapping()));
+
+        assert_eq!(<&str>::try_from(&seq), Err(wrong_type(ValueKind::String, ValueKind::Sequence)));
+        assert_eq!(<&Sequence>::try_from(&map), Err(wrong_type(ValueKind::Sequence, ValueKind::Mapping)));
+        assert_eq!(<&Block>::try_from(&seq), Err(wrong_type(ValueKind::Mapping, ValueKind::Sequence)));
+
+        assert_eq!(
+            wrong_type(ValueKind::Boolean, ValueKind::Integer).to_string(),
+            "expected value of kind Boolean, found kind Integer",
+        );
+    }
+
+    #[test]
+    fn to_pretty_string() {
+        let value = Value::Mapping(TU::core_nested_mapping());
+
+        let expected = r#"{
+  "boolean_key": true,
+  "decimal_key": 3.1415,
+  "integer_key": 27,
+  "mapping_key": {
+    "boolean_key": true,
+    "decimal_key": 3.1415,
+    "integer_key": 27,
+    "null_key": null,
+    "string_key": "string"
+  },
+  "null_key": null,
+  "sequence_key": [
+    "string",
+    27,
+    3.1415,
+    true,
+    null
+  ],
+  "string_key": "string"
+}"#;
+        assert_eq!(value.to_pretty_string(2), expected);
+
+        let expected = r#"{
+"boolean_key": true,
+"decimal_key": 3.1415,
+"integer_key": 27,
+"mapping_key": {
+"boolean_key": true,
+"decimal_key": 3.1415,
+"integer_key": 27,
+"null_key": null,
+"string_key": "string"
+},
+"null_key": null,
+"sequence_key": [
+"string",
+27,
+3.1415,
+true,
+null
+],
+"string_key": "string"
+}"#;
+        assert_eq!(value.to_pretty_string(0), expected);
+
+        // Scalars and empty collections.
+        assert_eq!(TU::s("say \"hi\"\n").to_pretty_string(2), r#""say \"hi\"\n""#);
+        assert_eq!(TU::i(-5).to_pretty_string(2), "-5");
+        assert_eq!(TU::d(dec!(1.500)).to_pretty_string(2), "1.5");
+        assert_eq!(TU::d(dec!(2.00)).to_pretty_string(2), "2.0");
+        assert_eq!(TU::d(dec!(-0.10)).to_pretty_string(2), "-0.1");
+        assert_eq!(Value::Sequence(vec![]).to_pretty_string(2), "[]");
+        assert_eq!(Value::Mapping(Block::new()).to_pretty_string(2), "{}");
+
+        // Deep nesting.
+        let mut value = TU::i(1);
+        for _ in 0..3 {
+            value = Value::Sequence(vec![value, Value::Sequence(vec![])]);
+        }
+        let expected = "[\n [\n  [\n   1,\n   []\n  ],\n  []\n ],\n []\n]";
+        assert_eq!(value.to_pretty_string(1), expected);
+    }
+
+    #[test]
+    fn merge() {
+        let base = Value::Mapping(Block(btreemap![
+            str!("title") => TU::s("base"),
+            str!("tags") => Value::Sequence(vec![TU::s("a"), TU::s("b")]),
+            str!("credits") => Value::Mapping(Block(btreemap![
+                str!("artist") => TU::s("base_artist"),
+                str!("roles") => Value::Mapping(Block(btreemap![
+                    str!("lead") => TU::s("base_lead"),
+                    str!("bass") => TU::s("base_bass"),
+                ])),
+            ])),
+            str!("scalar") => TU::i(1),
+            str!("mapping") => Value::Mapping(Block(btreemap![str!("key") => TU::i(1)])),
+        ]));
+        let over = Value::Mapping(Block(btreemap![
+            str!("tags") => Value::Sequence(vec![TU::s("c")]),
+            str!("credits") => Value::Mapping(Block(btreemap![
+                str!("roles") => Value::Mapping(Block(btreemap![
+                    str!("lead") => TU::s("over_lead"),
+                    str!("drums") => TU::s("over_drums"),
+                ])),
+            ])),
+            str!("scalar") => Value::Mapping(Block(btreemap![str!("key") => TU::i(2)])),
+            str!("mapping") => TU::i(2),
+            str!("extra") => Value::Null,
+        ]));
+        let expected = Value::Mapping(Block(btreemap![
+            str!("title") => TU::s("base"),
+            str!("tags") => Value::Sequence(vec![TU::s("c")]),
+            str!("credits") => Value::Mapping(Block(btreemap![
+                str!("artist") => TU::s("base_artist"),
+                str!("roles") => Value::Mapping(Block(btreemap![
+                    str!("lead") => TU::s("over_lead"),
+                    str!("bass") => TU::s("base_bass"),
+                    str!("drums") => TU::s("over_drums"),
+                ])),
+            ])),
+            str!("scalar") => Value::Mapping(Block(btreemap![str!("key") => TU::i(2)])),
+            str!("mapping") => TU::i(2),
+            str!("extra") => Value::Null,
+        ]));
+        assert_eq!(base.clone().merge(over), expected);
+
+        // Scalars override mappings, and vice versa.
+        assert_eq!(base.clone().merge(TU::s("scalar")), TU::s("scalar"));
+        assert_eq!(TU::s("scalar").merge(base.clone()), base);
+
+        // Merging empty mappings is a no-op.
+        let empty = Value::Mapping(Block::new());
+        assert_eq!(base.clone().merge(empty.clone()), base);
+        assert_eq!(empty.clone().merge(base.clone()), base);
+        assert_eq!(empty.clone().merge(empty.clone()), empty);
     }
 }
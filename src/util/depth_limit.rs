@@ -0,0 +1,466 @@
+//! A deserializer adaptor that enforces a maximum nesting depth while
+//! deserializing, instead of checking the depth of an already-built value.
+
+use std::cell::Cell;
+use std::fmt;
+
+use serde::de::{
+    self,
+    Deserialize,
+    DeserializeSeed,
+    Deserializer,
+    EnumAccess,
+    MapAccess,
+    SeqAccess,
+    VariantAccess,
+    Visitor,
+};
+
+/// Tracks the maximum nesting depth of sequences and mappings allowed while
+/// deserializing, and whether that depth was exceeded.
+/// Scalars have a depth of 0, and sequences and mappings are one deeper than
+/// their deepest child.
+pub(crate) struct DepthLimit {
+    max_depth: usize,
+    exceeded: Cell<bool>,
+}
+
+impl DepthLimit {
+    pub fn new(max_depth: usize) -> Self {
+        Self { max_depth, exceeded: Cell::new(false) }
+    }
+
+    /// Deserializes a value, failing as soon as a sequence or mapping would be
+    /// nested past the maximum depth, before any of its contents are read.
+    pub fn deserialize<'de, T, D>(&self, deserializer: D) -> Result<T, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        T::deserialize(Limited { inner: deserializer, depth: 0, limit: self })
+    }
+
+    /// Returns true if a deserialization failed due to exceeding the maximum
+    /// depth.
+    pub fn is_exceeded(&self) -> bool {
+        self.exceeded.get()
+    }
+
+    /// Returns the depth of a new sequence or mapping inside of a container at
+    /// a given depth, or an error if that is past the maximum depth.
+    fn enter<E: de::Error>(&self, depth: usize) -> Result<usize, E> {
+        let depth = depth + 1;
+
+        if depth > self.max_depth {
+            self.exceeded.set(true);
+            return Err(E::custom(format_args!("exceeded the maximum nesting depth of {}", self.max_depth)));
+        }
+
+        Ok(depth)
+    }
+}
+
+/// Wraps a deserializer, seed, visitor, or access, passing along the depth of
+/// the container that it is in.
+struct Limited<'l, T> {
+    inner: T,
+    depth: usize,
+    limit: &'l DepthLimit,
+}
+
+impl<'l, T> Limited<'l, T> {
+    fn wrap<U>(&self, inner: U) -> Limited<'l, U> {
+        Limited { inner, depth: self.depth, limit: self.limit }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let visitor = self.wrap(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, 'l, D> Deserializer<'de> for Limited<'l, D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<'de, 'l, S> DeserializeSeed<'de> for Limited<'l, S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let deserializer = self.wrap(deserializer);
+        self.inner.deserialize(deserializer)
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, 'l, V> Visitor<'de> for Limited<'l, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_string(String);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+        visit_byte_buf(Vec<u8>);
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_some(deserializer)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_newtype_struct(deserializer)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let depth = self.limit.enter(self.depth)?;
+        self.inner.visit_seq(Limited { inner: seq, depth, limit: self.limit })
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let depth = self.limit.enter(self.depth)?;
+        self.inner.visit_map(Limited { inner: map, depth, limit: self.limit })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let data = self.wrap(data);
+        self.inner.visit_enum(data)
+    }
+}
+
+impl<'de, 'l, A> SeqAccess<'de> for Limited<'l, A>
+where
+    A: SeqAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let seed = self.wrap(seed);
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'l, A> MapAccess<'de> for Limited<'l, A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let seed = self.wrap(seed);
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let seed = self.wrap(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'l, A> EnumAccess<'de> for Limited<'l, A>
+where
+    A: EnumAccess<'de>,
+{
+    type Error = A::Error;
+    type Variant = Limited<'l, A::Variant>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let seed = self.wrap(seed);
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        Ok((value, Limited { inner: variant, depth: self.depth, limit: self.limit }))
+    }
+}
+
+impl<'de, 'l, A> VariantAccess<'de> for Limited<'l, A>
+where
+    A: VariantAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let seed = self.wrap(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let visitor = self.wrap(visitor);
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let visitor = self.wrap(visitor);
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+/// Returns true if the arrays and inline tables of a TOML document are nested
+/// past a given depth. The TOML parser reads nested values recursively before
+/// any deserialization happens, so deeply nested input has to be rejected up
+/// front, using a lexical scan that skips over strings and comments.
+pub(crate) fn toml_nesting_exceeds(s: &str, max_depth: usize) -> bool {
+    let mut depth = 0usize;
+    let mut chars = s.chars().peekable();
+
+    // Consumes a string up to and including its closing delimiter.
+    fn skip_string<I>(chars: &mut std::iter::Peekable<I>, quote: char, escapes: bool)
+    where
+        I: Iterator<Item = char> + Clone,
+    {
+        let is_multiline = {
+            let mut ahead = chars.clone();
+            ahead.next() == Some(quote) && ahead.next() == Some(quote)
+        };
+
+        if is_multiline {
+            chars.next();
+            chars.next();
+        } else if chars.peek() == Some(&quote) {
+            // An empty single-line string.
+            chars.next();
+            return;
+        }
+
+        let mut run = 0;
+
+        while let Some(c) = chars.next() {
+            if escapes && c == '\\' {
+                chars.next();
+                run = 0;
+            } else if c == quote {
+                run += 1;
+
+                if !is_multiline || run == 3 {
+                    // Closing quotes may be followed by up to two more quotes
+                    // that are part of a multi-line string's content.
+                    while is_multiline && chars.peek() == Some(&quote) {
+                        chars.next();
+                    }
+                    return;
+                }
+            } else {
+                run = 0;
+
+                if !is_multiline && c == '\n' {
+                    return;
+                }
+            }
+        }
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' | '{' => {
+                depth += 1;
+
+                if depth > max_depth {
+                    return true;
+                }
+            },
+            ']' | '}' => depth = depth.saturating_sub(1),
+            '"' => skip_string(&mut chars, '"', true),
+            '\'' => skip_string(&mut chars, '\'', false),
+            '#' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::types::Value;
+
+    #[test]
+    fn depth_limit() {
+        let limit = DepthLimit::new(2);
+        let produced: Value = limit.deserialize(&mut serde_json::Deserializer::from_str("[[1], {}]")).unwrap();
+        assert_eq!(produced, serde_json::from_str::<Value>("[[1], {}]").unwrap());
+        assert!(!limit.is_exceeded());
+
+        let limit = DepthLimit::new(2);
+        let produced = limit.deserialize::<Value, _>(&mut serde_json::Deserializer::from_str("[[1], {\"a\": []}]"));
+        assert!(produced.is_err());
+        assert!(limit.is_exceeded());
+
+        // Other failures do not count as exceeding the limit.
+        let limit = DepthLimit::new(2);
+        let produced = limit.deserialize::<Value, _>(&mut serde_json::Deserializer::from_str("[[1], "));
+        assert!(produced.is_err());
+        assert!(!limit.is_exceeded());
+    }
+
+    #[test]
+    fn toml_nesting_exceeds() {
+        assert!(!super::toml_nesting_exceeds("a = [[1], {b = [2]}]", 3));
+        assert!(super::toml_nesting_exceeds("a = [[1], {b = [2]}]", 2));
+        assert!(!super::toml_nesting_exceeds("[[items]]\na = 1\n[[items]]\na = 2", 2));
+
+        // Brackets inside of strings and comments are skipped.
+        assert!(!super::toml_nesting_exceeds(r#"a = "[[[\"[[[" # [[["#, 0));
+        assert!(!super::toml_nesting_exceeds("a = '[[[\\'\nb = '''[[[''''\nc = \"\"\"[[[\n\"[[[\"\"\"\"", 0));
+        assert!(super::toml_nesting_exceeds("a = ''\nb = [[1]]", 1));
+    }
+}
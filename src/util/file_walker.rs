@@ -1,8 +1,10 @@
 use std::borrow::Cow;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::VecDeque;
 use std::path::Ancestors;
-use std::io::Error as IoError;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::config::{Selection, Sorter};
 
@@ -27,11 +29,11 @@ impl<'p> Iterator for FileWalker<'p> {
 }
 
 impl<'p> FileWalker<'p> {
-    pub fn delve(&mut self, selection: &Selection, sorter: &Sorter) -> Result<(), IoError> {
+    pub fn delve(&mut self) -> Result<(), IoError> {
         match self {
             // Parent walkers do not have to delve, just no-op.
             Self::Parent(..) => Ok(()),
-            Self::Child(ref mut fw) => fw.delve(selection, sorter),
+            Self::Child(ref mut fw) => fw.delve().map(|_| ()),
         }
     }
 }
@@ -50,12 +52,24 @@ impl<'p> From<ChildFileWalker<'p>> for FileWalker<'p> {
 
 /// A file walker that starts at an origin path, and walks up the directory tree.
 #[derive(Debug)]
-pub struct ParentFileWalker<'p>(Ancestors<'p>);
+pub struct ParentFileWalker<'p> {
+    ancestors: Ancestors<'p>,
+    remaining: Option<usize>,
+}
 
 impl<'p> ParentFileWalker<'p> {
     /// Constructs a new `ParentFileWalker` starting at a specified item path.
     pub fn new(origin_item_path: &'p Path) -> Self {
-        Self(origin_item_path.ancestors())
+        Self { ancestors: origin_item_path.ancestors(), remaining: None, }
+    }
+
+    /// Constructs a new `ParentFileWalker` starting at a specified item path,
+    /// that visits at most `max_ascents` ancestors of that path.
+    /// A value of zero visits only the origin item path itself.
+    pub fn with_max_ascents(origin_item_path: &'p Path, max_ascents: usize) -> Self {
+        // The origin item path is always visited, on top of the ancestors.
+        let remaining = Some(max_ascents.saturating_add(1));
+        Self { ancestors: origin_item_path.ancestors(), remaining, }
     }
 }
 
@@ -63,21 +77,57 @@ impl<'p> Iterator for ParentFileWalker<'p> {
     type Item = Cow<'p, Path>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(Cow::Borrowed)
+        if let Some(remaining) = self.remaining.as_mut() {
+            if *remaining == 0 { return None; }
+            *remaining -= 1;
+        }
+
+        self.ancestors.next().map(Cow::Borrowed)
     }
 }
 
+/// The order in which a `ChildFileWalker` visits the subitems it delves into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum TraversalOrder {
+    /// Subitems are visited right after their parent directory, before any of
+    /// the parent's later siblings.
+    #[default]
+    DepthFirst,
+    /// Subitems are visited after all items already in the frontier, i.e.
+    /// level by level.
+    BreadthFirst,
+}
+
 /// A file walker that starts at an origin path, with the ability to delve
 /// recursively into its directory structure to visit its children, grandchildren, etc.
+/// Subitems are selected and sorted using the `Selection` and `Sorter` that the
+/// walker was constructed with.
 #[derive(Debug)]
 pub struct ChildFileWalker<'p> {
     frontier: VecDeque<Result<Cow<'p, Path>, IoError>>,
     last_processed_path: Option<Cow<'p, Path>>,
+    selection: &'p Selection,
+    sorter: &'p Sorter,
+    order: TraversalOrder,
+    cancel: Option<Arc<AtomicBool>>,
+    cancelled: bool,
+    rejected: Vec<PathBuf>,
 }
 
 impl<'p> ChildFileWalker<'p> {
     /// Constructs a new `ChildFileWalker` starting at a specified item path.
-    pub fn new(origin_item_path: &'p Path) -> Self {
+    pub fn new(origin_item_path: &'p Path, selection: &'p Selection, sorter: &'p Sorter) -> Self {
+        Self::new_with_order(origin_item_path, selection, sorter, TraversalOrder::default())
+    }
+
+    /// Constructs a new `ChildFileWalker` starting at a specified item path,
+    /// that visits delved subitems in a specified order.
+    pub fn new_with_order(
+        origin_item_path: &'p Path,
+        selection: &'p Selection,
+        sorter: &'p Sorter,
+        order: TraversalOrder,
+    ) -> Self {
         let mut frontier = VecDeque::with_capacity(1);
 
         // Initialize the frontier with the origin item.
@@ -85,13 +135,48 @@ impl<'p> ChildFileWalker<'p> {
 
         let last_processed_path = None;
 
-        Self { frontier, last_processed_path, }
+        Self {
+            frontier,
+            last_processed_path,
+            selection,
+            sorter,
+            order,
+            cancel: None,
+            cancelled: false,
+            rejected: Vec::new(),
+        }
     }
 
-    /// Manually delves into a directory, and adds its subitems to the frontier.
+    /// Makes this walker cancellable from another thread. The flag is checked
+    /// before each item is produced; once it is set, the walker emits a single
+    /// error of kind `Interrupted` and then stops, discarding any remaining
+    /// frontier items.
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Returns true if this walker has stopped early due to cancellation.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Returns the subitems that were not selected during the most recent
+    /// delve, in directory listing order.
+    pub fn rejected(&self) -> &[PathBuf] {
+        &self.rejected
+    }
+
+    /// Manually delves into a directory, and adds its subitems to the frontier,
+    /// according to the traversal order of this walker.
+    /// Returns the number of subitems that were added.
     /// Note that this is a no-op if the most recent processed path is not a
     /// directory, and not an error.
-    pub fn delve(&mut self, selection: &Selection, sorter: &Sorter) -> Result<(), IoError> {
+    pub fn delve(&mut self) -> Result<usize, IoError> {
+        let mut num_added = 0;
+
+        self.rejected.clear();
+
         // If there is a last processed path, delve into it.
         // If not, just no-op.
         if let Some(lpp) = self.last_processed_path.take() {
@@ -100,16 +185,28 @@ impl<'p> ChildFileWalker<'p> {
 
             // Only work on directories.
             if file_info.is_dir() {
-                let mut sub_item_paths = selection.select_in_dir_sorted(&lpp, sorter)?;
-
-                // NOTE: Reversing and pushing onto the front of the queue is needed.
-                for p in sub_item_paths.drain(..).rev() {
-                    self.frontier.push_front(p.map(Cow::Owned));
+                let (mut sub_item_paths, rejected) = self.selection.select_in_dir_sorted_with_rejected(&lpp, self.sorter)?;
+
+                num_added = sub_item_paths.len();
+                self.rejected = rejected;
+
+                match self.order {
+                    TraversalOrder::DepthFirst => {
+                        // NOTE: Reversing and pushing onto the front of the queue is needed.
+                        for p in sub_item_paths.drain(..).rev() {
+                            self.frontier.push_front(p.map(Cow::Owned));
+                        }
+                    },
+                    TraversalOrder::BreadthFirst => {
+                        for p in sub_item_paths.drain(..) {
+                            self.frontier.push_back(p.map(Cow::Owned));
+                        }
+                    },
                 }
             }
         }
 
-        Ok(())
+        Ok(num_added)
     }
 }
 
@@ -117,6 +214,20 @@ impl<'p> Iterator for ChildFileWalker<'p> {
     type Item = Result<Cow<'p, Path>, IoError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.cancelled {
+            return None;
+        }
+
+        if let Some(cancel) = self.cancel.as_ref() {
+            if cancel.load(Ordering::Relaxed) {
+                self.cancelled = true;
+                self.frontier.clear();
+                self.last_processed_path = None;
+
+                return Some(Err(IoError::new(IoErrorKind::Interrupted, "file walk was cancelled")));
+            }
+        }
+
         let frontier_item_result = self.frontier.pop_front()?;
 
         // Save the most recently processed item path, if any.
@@ -147,6 +258,28 @@ mod tests {
         assert_eq!(walker.next().unwrap(), root_dir.path());
     }
 
+    #[test]
+    fn parent_file_walker_max_ascents() {
+        let root_dir = TestUtil::create_plain_fanout_test_dir("parent_file_walker_max_ascents", 3, 3);
+
+        let start_path = root_dir.path().join("0").join("0_1").join("0_1_0");
+
+        // Zero ascents only visits the origin item path.
+        let mut walker = ParentFileWalker::with_max_ascents(&start_path, 0);
+        assert_eq!(walker.next().unwrap(), start_path);
+        assert!(walker.next().is_none());
+
+        let mut walker = ParentFileWalker::with_max_ascents(&start_path, 2);
+        assert_eq!(walker.next().unwrap(), root_dir.path().join("0").join("0_1").join("0_1_0"));
+        assert_eq!(walker.next().unwrap(), root_dir.path().join("0").join("0_1"));
+        assert_eq!(walker.next().unwrap(), root_dir.path().join("0"));
+        assert!(walker.next().is_none());
+
+        // A bound higher than the number of ancestors stops at the filesystem root.
+        let walker = ParentFileWalker::with_max_ascents(&start_path, usize::MAX);
+        assert_eq!(walker.count(), start_path.ancestors().count());
+    }
+
     #[test]
     fn child_file_walker() {
         let root_dir = TestUtil::create_plain_fanout_test_dir("child_file_walker", 3, 3);
@@ -156,33 +289,37 @@ mod tests {
         // Skip the first file of each leaf directory.
         let selection = Selection::from_patterns(&["*_*"], &["*_0"], &["*"], &[] as &[&str]).unwrap();
         let sorter = Sorter::default();
-        let mut walker = ChildFileWalker::new(&start_path);
+        let mut walker = ChildFileWalker::new(&start_path, &selection, &sorter);
 
         // We should get just the root value, since no delving has happened.
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path());
         assert!(walker.next().is_none());
 
-        walker.delve(&selection, &sorter).unwrap();
+        walker.delve().unwrap();
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("0"));
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("1"));
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2"));
         assert!(walker.next().is_none());
 
         // This delve call opens up the most recently accessed directory.
-        walker.delve(&selection, &sorter).unwrap();
+        walker.delve().unwrap();
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2").join("2_0"));
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2").join("2_1"));
 
-        walker.delve(&selection, &sorter).unwrap();
+        walker.delve().unwrap();
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2").join("2_1").join("2_1_0"));
 
         // Once files are found, observe the results of the selection.
-        walker.delve(&selection, &sorter).unwrap();
+        walker.delve().unwrap();
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2").join("2_1").join("2_1_0").join("2_1_0_1"));
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2").join("2_1").join("2_1_0").join("2_1_0_2"));
 
+        // Paths that were not selected are reported.
+        assert_eq!(walker.rejected(), &[root_dir.path().join("2").join("2_1").join("2_1_0").join("2_1_0_0")]);
+
         // Delving on a file does nothing, and does not error.
-        walker.delve(&selection, &sorter).unwrap();
+        walker.delve().unwrap();
+        assert!(walker.rejected().is_empty());
 
         // Right back to where we were before delving into depth 3.
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2").join("2_1").join("2_1_1"));
@@ -190,4 +327,99 @@ mod tests {
         assert_eq!(walker.next().unwrap().unwrap(), root_dir.path().join("2").join("2_2"));
         assert!(walker.next().is_none());
     }
+
+    #[test]
+    fn child_file_walker_order() {
+        let root_dir = TestUtil::create_plain_fanout_test_dir("child_file_walker_order", 2, 2);
+        let root = root_dir.path();
+
+        let selection = Selection::default();
+        let sorter = Sorter::default();
+
+        // Delves after every item, and collects the paths relative to the root.
+        let walk = |order| {
+            let mut walker = ChildFileWalker::new_with_order(root, &selection, &sorter, order);
+            let mut visited = Vec::new();
+
+            while let Some(res) = walker.next() {
+                let path = res.unwrap();
+                visited.push(path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"));
+                walker.delve().unwrap();
+            }
+
+            visited
+        };
+
+        assert_eq!(walk(TraversalOrder::DepthFirst), vec![
+            "",
+            "0", "0/0_0", "0/0_0/0_0_0", "0/0_0/0_0_1", "0/0_1", "0/0_1/0_1_0", "0/0_1/0_1_1",
+            "1", "1/1_0", "1/1_0/1_0_0", "1/1_0/1_0_1", "1/1_1", "1/1_1/1_1_0", "1/1_1/1_1_1",
+        ]);
+
+        assert_eq!(walk(TraversalOrder::BreadthFirst), vec![
+            "",
+            "0", "1",
+            "0/0_0", "0/0_1", "1/1_0", "1/1_1",
+            "0/0_0/0_0_0", "0/0_0/0_0_1", "0/0_1/0_1_0", "0/0_1/0_1_1",
+            "1/1_0/1_0_0", "1/1_0/1_0_1", "1/1_1/1_1_0", "1/1_1/1_1_1",
+        ]);
+
+        // The default order is depth-first.
+        assert_eq!(ChildFileWalker::new(root, &selection, &sorter).order, TraversalOrder::DepthFirst);
+    }
+
+    #[test]
+    fn child_file_walker_cancel() {
+        let root_dir = TestUtil::create_plain_fanout_test_dir("child_file_walker_cancel", 3, 3);
+
+        let start_path = root_dir.path();
+
+        let selection = Selection::default();
+        let sorter = Sorter::default();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut walker = ChildFileWalker::new(start_path, &selection, &sorter).with_cancel(Arc::clone(&cancel));
+
+        let num_before_cancel = 5;
+        let mut num_visited = 0;
+        let mut num_errors = 0;
+
+        while let Some(res) = walker.next() {
+            if res.is_err() {
+                num_errors += 1;
+                continue;
+            }
+
+            num_visited += 1;
+
+            if num_visited == num_before_cancel {
+                cancel.store(true, Ordering::Relaxed);
+            }
+
+            walker.delve().unwrap();
+        }
+
+        // The walk should have been stopped early, emitting an error.
+        assert_eq!(num_visited, num_before_cancel);
+        assert_eq!(num_errors, 1);
+        assert!(walker.is_cancelled());
+
+        // Once the walk stops, it stays stopped.
+        assert!(walker.next().is_none());
+
+        // Check that the cancellation error is emitted exactly once.
+        let cancel = Arc::new(AtomicBool::new(true));
+        let mut walker = ChildFileWalker::new(start_path, &selection, &sorter).with_cancel(cancel);
+        match walker.next() {
+            Some(Err(err)) => assert_eq!(err.kind(), IoErrorKind::Interrupted),
+            _ => panic!("expected cancellation error"),
+        }
+        assert!(walker.next().is_none());
+
+        // Cancellation combines with a traversal order.
+        let cancel = Arc::new(AtomicBool::new(true));
+        let mut walker = ChildFileWalker::new_with_order(start_path, &selection, &sorter, TraversalOrder::BreadthFirst).with_cancel(cancel);
+        assert_eq!(walker.order, TraversalOrder::BreadthFirst);
+        assert!(matches!(walker.next(), Some(Err(..))));
+        assert!(walker.is_cancelled());
+    }
 }
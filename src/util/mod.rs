@@ -1,4 +1,5 @@
 pub mod file_walker;
+pub(crate) mod depth_limit;
 pub(crate) mod ooms;
 
 pub use self::file_walker::FileWalker;
@@ -48,6 +49,12 @@ impl Util {
         abs_path.metadata().and_then(|m| m.modified()).ok()
     }
 
+    /// Convenience method that gets the size in bytes of a path.
+    /// Errors are coerced to `None`.
+    pub fn file_size(abs_path: &Path) -> Option<u64> {
+        abs_path.metadata().map(|m| m.len()).ok()
+    }
+
     /// Tests a string to see if it would be a valid item file name.
     pub fn validate_item_name(name: &str) -> Result<(), InvalidNameKind> {
         // Re-create this name as a file path, and iterate over its components.